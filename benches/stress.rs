@@ -0,0 +1,207 @@
+//! Stress test for the runner and the event loop.
+//!
+//! Spawns `BENCH_WINDOWS` layer surfaces (16 by default), feeds synthetic pointer motion into them
+//! and wakes the runner from another thread. After `BENCH_SECONDS` (10 by default) the frame time,
+//! the latency between a wake up and the frame handling it, and the resident memory are printed.
+//!
+//! Needs a running compositor which supports the layer shell:
+//!
+//! ```sh
+//! BENCH_WINDOWS=64 BENCH_SECONDS=30 cargo bench --bench stress
+//! ```
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    prelude::*,
+    render::camera::RenderTarget,
+    window::{ExitCondition, WindowRef, WindowResolution},
+    winit::WinitPlugin,
+};
+use bevy_wayland::{prelude::*, ExternalEventDispatcher};
+
+const WINDOW_WIDTH: f32 = 200.0;
+const WINDOW_HEIGHT: f32 = 100.0;
+const COLUMNS: usize = 8;
+const WAKEUP_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Resource)]
+struct BenchConfig {
+    windows: usize,
+    duration: Duration,
+}
+
+/// Send times of the wake ups which weren't handled by a frame yet.
+#[derive(Resource, Default, Clone)]
+struct PendingWakeups(Arc<Mutex<Vec<Instant>>>);
+
+#[derive(Resource, Default)]
+struct Stats {
+    start: Option<Instant>,
+    /// Frame times in milliseconds.
+    frame_times: Vec<f32>,
+    /// Wake up latencies in milliseconds.
+    latencies: Vec<f32>,
+    peak_rss: u64,
+}
+
+fn main() {
+    let config = BenchConfig {
+        windows: env_or("BENCH_WINDOWS", 16),
+        duration: Duration::from_secs(env_or("BENCH_SECONDS", 10)),
+    };
+
+    App::new()
+        .add_plugins((
+            DefaultPlugins
+                .build()
+                .disable::<WinitPlugin>()
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    ..Default::default()
+                }),
+            WaylandPlugins,
+        ))
+        .insert_resource(config)
+        .init_resource::<PendingWakeups>()
+        .init_resource::<Stats>()
+        .add_systems(Startup, (spawn_windows, spawn_wakeup_thread))
+        .add_systems(PreUpdate, record_wakeups)
+        .add_systems(Update, (synthetic_input, record_frame))
+        .run();
+}
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn spawn_windows(mut commands: Commands, config: Res<BenchConfig>) {
+    for index in 0..config.windows {
+        let (row, column) = (index / COLUMNS, index % COLUMNS);
+        let window = commands
+            .spawn((
+                Window {
+                    resolution: WindowResolution::new(WINDOW_WIDTH, WINDOW_HEIGHT),
+                    ..Default::default()
+                },
+                LayerShellSettings {
+                    anchor: Anchor::TOP | Anchor::LEFT,
+                    margin: (
+                        row as i32 * WINDOW_HEIGHT as i32,
+                        0,
+                        0,
+                        column as i32 * WINDOW_WIDTH as i32,
+                    ),
+                    keyboard_interactivity: KeyboardInteractivity::None,
+                    layer: Layer::Bottom,
+                    ..Default::default()
+                },
+            ))
+            .id();
+        commands.spawn((
+            Camera2d,
+            Camera {
+                target: RenderTarget::Window(WindowRef::Entity(window)),
+                clear_color: ClearColorConfig::Custom(Color::hsl(
+                    index as f32 * 360.0 / config.windows as f32,
+                    0.6,
+                    0.5,
+                )),
+                ..Default::default()
+            },
+        ));
+    }
+}
+
+fn spawn_wakeup_thread(
+    external_event_dispatcher: Res<ExternalEventDispatcher>,
+    pending_wakeups: Res<PendingWakeups>,
+) {
+    let dispatcher = external_event_dispatcher.clone();
+    let pending_wakeups = pending_wakeups.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(WAKEUP_INTERVAL);
+        pending_wakeups.0.lock().unwrap().push(Instant::now());
+        if dispatcher.dispatch().is_err() {
+            return;
+        }
+    });
+}
+
+fn record_wakeups(pending_wakeups: Res<PendingWakeups>, mut stats: ResMut<Stats>) {
+    let now = Instant::now();
+    let sent = std::mem::take(&mut *pending_wakeups.0.lock().unwrap());
+    stats.latencies.extend(
+        sent.into_iter()
+            .map(|sent| (now - sent).as_secs_f32() * 1000.0),
+    );
+}
+
+/// Moves the cursor in a circle over every window, which keeps picking and the UI busy.
+fn synthetic_input(
+    time: Res<Time>,
+    windows: Query<Entity, With<Window>>,
+    mut cursor_moved: EventWriter<CursorMoved>,
+) {
+    let angle = time.elapsed_secs() * std::f32::consts::TAU;
+    let position = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT) / 2.0
+        + Vec2::from_angle(angle) * WINDOW_HEIGHT / 4.0;
+    for window in &windows {
+        cursor_moved.write(CursorMoved {
+            window,
+            position,
+            delta: None,
+        });
+    }
+}
+
+fn record_frame(time: Res<Time>, config: Res<BenchConfig>, mut stats: ResMut<Stats>) {
+    let start = *stats.start.get_or_insert_with(Instant::now);
+    stats.frame_times.push(time.delta_secs() * 1000.0);
+    if let Some(rss) = resident_memory() {
+        stats.peak_rss = stats.peak_rss.max(rss);
+    }
+    if start.elapsed() < config.duration {
+        return;
+    }
+
+    println!("windows:        {}", config.windows);
+    println!("frames:         {}", stats.frame_times.len());
+    print_summary("frame time", &mut stats.frame_times);
+    print_summary("wake latency", &mut stats.latencies);
+    println!(
+        "peak memory:    {:.1} MiB",
+        stats.peak_rss as f64 / (1024.0 * 1024.0)
+    );
+    std::process::exit(0);
+}
+
+fn print_summary(name: &str, samples: &mut [f32]) {
+    if samples.is_empty() {
+        println!("{name}: no samples");
+        return;
+    }
+    samples.sort_by(f32::total_cmp);
+    let percentile = |p: f32| samples[((samples.len() - 1) as f32 * p).round() as usize];
+    let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+    println!(
+        "{name:<15} mean {mean:.2} ms, p50 {:.2} ms, p99 {:.2} ms, max {:.2} ms",
+        percentile(0.5),
+        percentile(0.99),
+        percentile(1.0),
+    );
+}
+
+/// Returns the resident memory of the process in bytes, assuming 4 KiB pages.
+fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
@@ -21,7 +21,7 @@ fn main() {
                     }),
                     ..Default::default()
                 }),
-            WaylandPlugin,
+            WaylandPlugins,
         ))
         .add_systems(Startup, (setup, external_tick_sender))
         .add_systems(Update, (button_system, exit_on_esc))
@@ -19,7 +19,7 @@ fn main() {
                     }),
                     ..Default::default()
                 }),
-            WaylandPlugin,
+            WaylandPlugins,
         ))
         .add_systems(Startup, setup)
         .add_systems(Update, (button_system, exit_on_esc))
@@ -19,7 +19,7 @@ fn main() {
                     primary_window: None,
                     ..Default::default()
                 }),
-            WaylandPlugin,
+            WaylandPlugins,
         ))
         .add_systems(Startup, setup)
         .add_systems(
@@ -28,6 +28,7 @@ fn main() {
                 button_system,
                 exit_on_esc,
                 setup_session_lock_windows,
+                cleanup_after_unlock,
                 exit_on_all_closed,
             ),
         )
@@ -97,6 +98,8 @@ fn setup(
 struct ConfiguredWindow;
 #[derive(Component)]
 struct SessionLockCamera;
+#[derive(Component)]
+struct SessionLockUi;
 
 fn setup_session_lock_windows(
     mut commands: Commands,
@@ -119,6 +122,21 @@ fn setup_session_lock_windows(
     }
 }
 
+#[allow(clippy::type_complexity)]
+fn cleanup_after_unlock(
+    mut commands: Commands,
+    mut session_lock_events: EventReader<SessionLockEvent>,
+    lock_entities: Query<Entity, Or<(With<SessionLockCamera>, With<SessionLockUi>)>>,
+) {
+    for session_lock_event in session_lock_events.read() {
+        if let SessionLockEvent::Unlocked = session_lock_event {
+            for entity in &lock_entities {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
 fn exit_on_esc(keys: Res<ButtonInput<KeyCode>>) {
     if keys.just_pressed(KeyCode::Escape) {
         std::process::exit(0);
@@ -174,6 +192,7 @@ fn unlock_button(asset_server: &AssetServer, camera: Entity) -> impl Bundle + us
             ..default()
         },
         UiTargetCamera(camera),
+        SessionLockUi,
         children![(
             Button,
             UnlockButton,
@@ -1,11 +1,4 @@
-use std::time::Duration;
-
-use bevy::{
-    color::palettes::basic::*,
-    prelude::*,
-    window::{WindowCreated, WindowResolution},
-    winit::WinitPlugin,
-};
+use bevy::{color::palettes::basic::*, prelude::*, window::WindowResolution, winit::WinitPlugin};
 use bevy_wayland::prelude::*;
 
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
@@ -25,7 +18,7 @@ fn main() {
                     }),
                     ..Default::default()
                 }),
-            WaylandPlugin,
+            WaylandPlugins,
         ))
         .add_systems(Startup, setup)
         .add_systems(Update, (button_system, exit_on_esc))
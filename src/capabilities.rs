@@ -0,0 +1,48 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+use smithay_client_toolkit::reexports::client::globals::GlobalList;
+
+/// Optional protocols bound by the plugins of this crate, so apps can degrade gracefully when one
+/// is missing.
+///
+/// Every flag is set by the plugin which binds the protocol once the bind succeeded, so it stays
+/// `false` if the compositor doesn't offer the protocol in a supported version, or if the plugin
+/// isn't added. Read the flags after the plugins were built, e.g. in `Startup` systems.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct WaylandCapabilities {
+    /// `zwlr_layer_shell_v1`, bound by [`LayerShellPlugin`](crate::layer_shell::LayerShellPlugin).
+    pub layer_shell: bool,
+    /// `ext_session_lock_manager_v1`, bound by
+    /// [`SessionLockPlugin`](crate::session_lock::SessionLockPlugin), see
+    /// [`SessionLockSecurity`](crate::session_lock::SessionLockSecurity).
+    pub session_lock: bool,
+    /// `zwlr_foreign_toplevel_manager_v1`, needed for `ForeignToplevelEvent`.
+    pub foreign_toplevel_manager: bool,
+    /// `zwlr_output_manager_v1`, needed for `OutputTransformEvent`.
+    pub output_management: bool,
+    /// `wp_viewporter`, needed for [`SurfaceViewport`](crate::surface_viewport::SurfaceViewport).
+    pub viewporter: bool,
+    /// Highest version of every global advertised by the compositor, by interface name, whether
+    /// it is bound or not.
+    pub globals: HashMap<String, u32>,
+}
+impl WaylandCapabilities {
+    pub(crate) fn new(globals: &GlobalList) -> Self {
+        let mut versions = HashMap::<String, u32>::default();
+        globals.contents().with_list(|globals| {
+            for global in globals {
+                let version = versions.entry(global.interface.clone()).or_default();
+                *version = (*version).max(global.version);
+            }
+        });
+
+        Self {
+            globals: versions,
+            ..default()
+        }
+    }
+
+    /// Returns the version of the global with the given interface name, if it is advertised.
+    pub fn version(&self, interface: &str) -> Option<u32> {
+        self.globals.get(interface).copied()
+    }
+}
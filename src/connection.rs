@@ -0,0 +1,87 @@
+use std::{
+    env,
+    ops::RangeInclusive,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+};
+
+use smithay_client_toolkit::reexports::client::{
+    globals::{registry_queue_init, BindError, GlobalList, GlobalListContents},
+    protocol::wl_registry::{self, WlRegistry},
+    ConnectError, Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+
+use crate::WaylandState;
+
+/// Connects to `socket`, which is resolved relative to `XDG_RUNTIME_DIR` unless it is absolute,
+/// the same way as `WAYLAND_DISPLAY`.
+pub(crate) fn connect_to_socket(socket: &Path) -> Result<Connection, ConnectError> {
+    let path = if socket.is_absolute() {
+        socket.to_path_buf()
+    } else {
+        let runtime_dir = env::var_os("XDG_RUNTIME_DIR").ok_or(ConnectError::NoCompositor)?;
+        PathBuf::from(runtime_dir).join(socket)
+    };
+    let stream = UnixStream::connect(path).map_err(|_| ConnectError::NoCompositor)?;
+    Connection::from_socket(stream)
+}
+
+/// A second connection, e.g. to the parent compositor of a nested compositor, which privileged
+/// protocols like the foreign toplevel and output management are bound on instead of the main one.
+///
+/// Inserted by [`WaylandPlugin`](crate::WaylandPlugin) if
+/// [`secondary_socket`](crate::WaylandPlugin::secondary_socket) is set. Its events are dispatched
+/// to the same [`WaylandState`] as the ones of the main connection.
+pub struct SecondaryConnection {
+    connection: Connection,
+    globals: GlobalList,
+    queue_handle: QueueHandle<WaylandState>,
+    /// Owns the registry, which is only needed to bind the globals advertised at connection time.
+    _registry_queue: EventQueue<SecondaryRegistry>,
+}
+impl SecondaryConnection {
+    pub(crate) fn new(
+        connection: Connection,
+        queue_handle: QueueHandle<WaylandState>,
+    ) -> Result<Self, crate::error::WaylandError> {
+        let (globals, registry_queue) = registry_queue_init::<SecondaryRegistry>(&connection)?;
+        Ok(Self {
+            connection,
+            globals,
+            queue_handle,
+            _registry_queue: registry_queue,
+        })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn queue_handle(&self) -> &QueueHandle<WaylandState> {
+        &self.queue_handle
+    }
+
+    /// Binds a global of the secondary connection, see [`GlobalList::bind`].
+    pub fn bind<I, U>(&self, version: RangeInclusive<u32>, udata: U) -> Result<I, BindError>
+    where
+        I: Proxy + 'static,
+        U: Send + Sync + 'static,
+        WaylandState: Dispatch<I, U>,
+    {
+        self.globals.bind(&self.queue_handle, version, udata)
+    }
+}
+
+/// State of the registry queue of the secondary connection, which is never dispatched.
+pub(crate) struct SecondaryRegistry;
+impl Dispatch<WlRegistry, GlobalListContents> for SecondaryRegistry {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
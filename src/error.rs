@@ -0,0 +1,86 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use smithay_client_toolkit::reexports::{
+    calloop,
+    client::{
+        globals::{BindError, GlobalError},
+        ConnectError,
+    },
+};
+
+/// Called with the error when the wayland connection can't be set up or a global required by a
+/// plugin is missing, see [`WaylandPlugin::error_handler`](crate::WaylandPlugin::error_handler).
+///
+/// The plugin that failed doesn't add any of its systems or resources.
+pub type WaylandErrorHandler = fn(&mut App, WaylandError);
+
+#[derive(Debug)]
+pub enum WaylandError {
+    /// Couldn't connect to the compositor.
+    Connect(ConnectError),
+    /// Couldn't create the event loop or register an event source in it.
+    EventLoop(calloop::Error),
+    /// Couldn't retrieve the globals of the compositor.
+    Registry(GlobalError),
+    /// A global required by a plugin isn't advertised by the compositor.
+    MissingGlobal {
+        interface: &'static str,
+        error: BindError,
+    },
+}
+impl fmt::Display for WaylandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(error) => write!(f, "Failed to connect to wayland socket: {error}"),
+            Self::EventLoop(error) => write!(f, "Failed to set up the event loop: {error}"),
+            Self::Registry(error) => write!(f, "Failed to init registry queue: {error}"),
+            Self::MissingGlobal { interface, error } => {
+                write!(f, "Failed to bind {interface}: {error}")
+            }
+        }
+    }
+}
+impl std::error::Error for WaylandError {}
+impl From<ConnectError> for WaylandError {
+    fn from(error: ConnectError) -> Self {
+        Self::Connect(error)
+    }
+}
+impl From<calloop::Error> for WaylandError {
+    fn from(error: calloop::Error) -> Self {
+        Self::EventLoop(error)
+    }
+}
+impl From<GlobalError> for WaylandError {
+    fn from(error: GlobalError) -> Self {
+        Self::Registry(error)
+    }
+}
+
+/// The default [`WaylandErrorHandler`], which panics.
+pub fn panic_on_error(_app: &mut App, error: WaylandError) {
+    panic!("{error}");
+}
+
+#[derive(Resource, Clone, Copy)]
+pub(crate) struct ErrorHandler(pub(crate) WaylandErrorHandler);
+
+/// Passes `error` to the [`WaylandErrorHandler`] of the app.
+pub(crate) fn report_error(app: &mut App, error: WaylandError) {
+    let error_handler = app
+        .world()
+        .get_resource::<ErrorHandler>()
+        .map_or(panic_on_error as WaylandErrorHandler, |error_handler| {
+            error_handler.0
+        });
+    error_handler(app, error);
+}
+
+/// Returns whether [`WaylandPlugin`](crate::WaylandPlugin) connected to the compositor, plugins
+/// depending on the connection skip their setup otherwise.
+pub(crate) fn is_connected(app: &App) -> bool {
+    app.world()
+        .get_non_send_resource::<smithay_client_toolkit::reexports::client::Connection>()
+        .is_some()
+}
@@ -8,7 +8,10 @@ use wayland_protocols_wlr::foreign_toplevel::v1::client::{
     zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
 };
 
-use crate::WaylandState;
+use crate::{
+    capabilities::WaylandCapabilities, connection::SecondaryConnection, error::is_connected,
+    WaylandState,
+};
 #[derive(Debug, Copy, Clone, Event)]
 pub enum ForeignToplevelEvent {
     MinimizeOthers,
@@ -20,12 +23,27 @@ struct ForeignToplevels(Vec<ZwlrForeignToplevelHandleV1>);
 pub struct ForeignToplevelManagerPlugin;
 impl Plugin for ForeignToplevelManagerPlugin {
     fn build(&self, app: &mut App) {
+        if !is_connected(app) {
+            return;
+        }
         let registry_state = app.world().non_send_resource::<RegistryState>();
         let queue_handle = app.world().non_send_resource::<QueueHandle<WaylandState>>();
         let foreign_top_level_manager =
-            registry_state.bind_one::<ZwlrForeignToplevelManagerV1, _, _>(queue_handle, 2..=3, ());
+            match app.world().get_non_send_resource::<SecondaryConnection>() {
+                Some(secondary_connection) => {
+                    secondary_connection.bind::<ZwlrForeignToplevelManagerV1, _>(2..=3, ())
+                }
+                None => registry_state.bind_one::<ZwlrForeignToplevelManagerV1, _, _>(
+                    queue_handle,
+                    2..=3,
+                    (),
+                ),
+            };
         if let Ok(foreign_top_level_manager) = foreign_top_level_manager {
             info!("Foreign toplevel manager was bound!");
+            app.world_mut()
+                .resource_mut::<WaylandCapabilities>()
+                .foreign_toplevel_manager = true;
             app.insert_non_send_resource(foreign_top_level_manager);
             app.insert_non_send_resource(ForeignToplevels::default());
             app.add_event::<ForeignToplevelEvent>();
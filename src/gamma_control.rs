@@ -0,0 +1,264 @@
+use std::{
+    fs::File,
+    io::{Seek, Write},
+    os::fd::AsFd,
+};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use rustix::fs::{memfd_create, MemfdFlags};
+use smithay_client_toolkit::{
+    output::OutputState,
+    reexports::client::{backend::ObjectId, Dispatch, Proxy, QueueHandle},
+    registry::RegistryState,
+};
+use wayland_protocols_wlr::gamma_control::v1::client::{
+    zwlr_gamma_control_manager_v1::{self, ZwlrGammaControlManagerV1},
+    zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+};
+
+use crate::{error::is_connected, WaylandState};
+
+/// Applies gamma ramps to outputs through wlr-gamma-control, e.g. the curves of a calibrated panel.
+///
+/// A ramp stays applied until it is reset or the app exits, after which the compositor restores
+/// the previous one.
+#[derive(Debug, Clone, Event)]
+pub enum OutputGammaEvent {
+    /// Sets the ramp of the output with the given name, or of every output if `output_name` is
+    /// `None`.
+    Set {
+        output_name: Option<String>,
+        ramp: GammaRamp,
+    },
+    /// Restores the ramp the output had before it was set.
+    Reset { output_name: Option<String> },
+}
+
+/// Lookup tables mapping each channel from black to white, resampled to the size the output uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GammaRamp {
+    pub red: Vec<u16>,
+    pub green: Vec<u16>,
+    pub blue: Vec<u16>,
+}
+impl GammaRamp {
+    const SIZE: usize = 256;
+
+    /// A ramp raising each channel to the power of `1 / gamma`, 1.0 being linear.
+    pub fn from_gamma(red: f32, green: f32, blue: f32) -> Self {
+        let channel = |gamma: f32| {
+            (0..Self::SIZE)
+                .map(|i| {
+                    let value = (i as f32 / (Self::SIZE - 1) as f32).powf(1.0 / gamma);
+                    (value * u16::MAX as f32).round() as u16
+                })
+                .collect()
+        };
+        Self {
+            red: channel(red),
+            green: channel(green),
+            blue: channel(blue),
+        }
+    }
+
+    /// Returns the tables of all channels with `size` entries each, linearly interpolated.
+    fn resampled(&self, size: usize) -> Vec<u16> {
+        [&self.red, &self.green, &self.blue]
+            .into_iter()
+            .flat_map(|channel| resample(channel, size))
+            .collect()
+    }
+}
+
+fn resample(channel: &[u16], size: usize) -> impl Iterator<Item = u16> + '_ {
+    (0..size).map(move |i| {
+        if channel.len() < 2 || size < 2 {
+            return channel.first().copied().unwrap_or_default();
+        }
+        let position = i as f32 * (channel.len() - 1) as f32 / (size - 1) as f32;
+        let index = (position as usize).min(channel.len() - 2);
+        let fraction = position - index as f32;
+        let start = channel[index] as f32;
+        let end = channel[index + 1] as f32;
+        (start + (end - start) * fraction).round() as u16
+    })
+}
+
+struct GammaControl {
+    control: ZwlrGammaControlV1,
+    /// Received from the compositor after the control was created.
+    gamma_size: Option<u32>,
+    /// Applied once the gamma size is known.
+    pending_ramp: Option<GammaRamp>,
+}
+impl GammaControl {
+    fn set_ramp(&mut self, ramp: GammaRamp) {
+        let Some(gamma_size) = self.gamma_size else {
+            self.pending_ramp = Some(ramp);
+            return;
+        };
+        let table: Vec<u8> = ramp
+            .resampled(gamma_size as usize)
+            .into_iter()
+            .flat_map(u16::to_ne_bytes)
+            .collect();
+        let file = memfd_create("gamma-ramp", MemfdFlags::CLOEXEC)
+            .map(File::from)
+            .map_err(std::io::Error::from)
+            .and_then(|mut file| {
+                file.write_all(&table)?;
+                file.rewind()?;
+                Ok(file)
+            });
+        match file {
+            Ok(file) => self.control.set_gamma(file.as_fd()),
+            Err(error) => error!("Unable to create gamma ramp: {}", error),
+        }
+    }
+}
+
+/// Gamma controls by the id of their output.
+#[derive(Default, Deref, DerefMut)]
+struct GammaControls(HashMap<ObjectId, GammaControl>);
+
+pub struct GammaControlPlugin;
+impl Plugin for GammaControlPlugin {
+    fn build(&self, app: &mut App) {
+        if !is_connected(app) {
+            return;
+        }
+        let registry_state = app.world().non_send_resource::<RegistryState>();
+        let queue_handle = app.world().non_send_resource::<QueueHandle<WaylandState>>();
+        let gamma_control_manager =
+            registry_state.bind_one::<ZwlrGammaControlManagerV1, _, _>(queue_handle, 1..=1, ());
+        match gamma_control_manager {
+            Ok(gamma_control_manager) => {
+                info!("Gamma control manager was bound!");
+                app.insert_non_send_resource(gamma_control_manager);
+                app.insert_non_send_resource(GammaControls::default());
+                app.add_event::<OutputGammaEvent>();
+                app.add_systems(Update, output_gamma_event_handler);
+            }
+            Err(bind_error) => {
+                error!("Couldn't bind gamma control manager! {:?}", bind_error);
+            }
+        }
+    }
+}
+
+fn output_gamma_event_handler(
+    gamma_control_manager: NonSend<ZwlrGammaControlManagerV1>,
+    mut gamma_controls: NonSendMut<GammaControls>,
+    output_state: NonSend<OutputState>,
+    queue_handle: NonSend<QueueHandle<WaylandState>>,
+    mut events: EventReader<OutputGammaEvent>,
+) {
+    for event in events.read() {
+        let (OutputGammaEvent::Set { output_name, .. } | OutputGammaEvent::Reset { output_name }) =
+            event;
+        let outputs = output_state.outputs().filter(|output| {
+            output_name.as_ref().is_none_or(|output_name| {
+                output_state
+                    .info(output)
+                    .is_some_and(|info| info.name.as_ref() == Some(output_name))
+            })
+        });
+        for output in outputs {
+            match event {
+                OutputGammaEvent::Set { ramp, .. } => {
+                    gamma_controls
+                        .entry(output.id())
+                        .or_insert_with(|| GammaControl {
+                            control: gamma_control_manager.get_gamma_control(
+                                &output,
+                                &queue_handle,
+                                output.id(),
+                            ),
+                            gamma_size: None,
+                            pending_ramp: None,
+                        })
+                        .set_ramp(ramp.clone());
+                }
+                OutputGammaEvent::Reset { .. } => {
+                    // Destroying the control restores the original ramp.
+                    if let Some(gamma_control) = gamma_controls.remove(&output.id()) {
+                        gamma_control.control.destroy();
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwlrGammaControlManagerV1,
+        _event: zwlr_gamma_control_manager_v1::Event,
+        _data: &(),
+        _conn: &smithay_client_toolkit::reexports::client::Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, ObjectId> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrGammaControlV1,
+        event: zwlr_gamma_control_v1::Event,
+        output_id: &ObjectId,
+        _conn: &smithay_client_toolkit::reexports::client::Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let mut gamma_controls = state.world_mut().non_send_resource_mut::<GammaControls>();
+        match event {
+            zwlr_gamma_control_v1::Event::GammaSize { size } => {
+                let Some(gamma_control) = gamma_controls.get_mut(output_id) else {
+                    return;
+                };
+                gamma_control.gamma_size = Some(size);
+                if let Some(ramp) = gamma_control.pending_ramp.take() {
+                    gamma_control.set_ramp(ramp);
+                }
+            }
+            zwlr_gamma_control_v1::Event::Failed => {
+                warn!("Gamma control failed, the output is gone or controlled by another client");
+                if let Some(gamma_control) = gamma_controls.remove(output_id) {
+                    gamma_control.control.destroy();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resample_keeps_same_size() {
+        let channel = [0, 1000, 40000, u16::MAX];
+        assert_eq!(resample(&channel, 4).collect::<Vec<_>>(), channel);
+    }
+
+    #[test]
+    fn resample_interpolates_linearly() {
+        assert_eq!(resample(&[0, 100], 3).collect::<Vec<_>>(), [0, 50, 100]);
+        assert_eq!(resample(&[0, 50, 100], 2).collect::<Vec<_>>(), [0, 100]);
+    }
+
+    #[test]
+    fn resample_repeats_single_entry() {
+        assert_eq!(resample(&[42], 3).collect::<Vec<_>>(), [42, 42, 42]);
+        assert_eq!(resample(&[], 2).collect::<Vec<_>>(), [0, 0]);
+    }
+
+    #[test]
+    fn linear_ramp_covers_full_range() {
+        let ramp = GammaRamp::from_gamma(1.0, 1.0, 1.0);
+        let table = ramp.resampled(3);
+        assert_eq!(table, [0, 32768, u16::MAX].repeat(3));
+    }
+}
@@ -1,33 +1,77 @@
-use bevy::input::keyboard::Key;
+use bevy::input::keyboard::{Key, NativeKey, NativeKeyCode};
 use bevy::input::{keyboard::KeyboardInput, ButtonState};
-use bevy::log::warn;
 use bevy::prelude::*;
-use bevy::window::WindowEvent;
+use bevy::window::{WindowEvent, WindowFocused};
 use smithay_client_toolkit::reexports::client::Proxy;
 use smithay_client_toolkit::{
     delegate_keyboard,
     seat::keyboard::{KeyEvent, KeyboardHandler, Keysym},
 };
 
+use super::keyboard_layout::{KeyboardLayoutState, LayoutChanged};
 use crate::surface_handler::WaylandSurfaces;
 use crate::WaylandState;
 
 /// Converts a Smithay keyboard event to a Bevy keyboard input event.
+///
+/// Smithay runs the xkb compose state machine, so `event.utf8` already holds the result of a
+/// completed dead-key or compose sequence and is `None` while a sequence is still in progress.
 fn convert_keyboard_event(
     event: KeyEvent,
     entity: Entity,
     state: ButtonState,
 ) -> bevy::input::keyboard::KeyboardInput {
+    let text = event
+        .utf8
+        .filter(|text| !text.chars().any(char::is_control));
+    let logical_key = match &text {
+        Some(text) => Key::Character(text.as_str().into()),
+        None => convert_to_logical_key(event.keysym),
+    };
+    let key_code = if is_compose_key(event.keysym) {
+        // The physical key can't be derived from a dead keysym, it depends on the layout.
+        KeyCode::Unidentified(NativeKeyCode::Xkb(event.raw_code))
+    } else {
+        convert_to_key_code(event.keysym)
+    };
     KeyboardInput {
         state,
-        text: None,
+        text: text.map(Into::into),
         window: entity,
-        key_code: convert_to_key_code(event.keysym),
-        logical_key: convert_to_logical_key(event.keysym),
+        key_code,
+        logical_key,
         repeat: false,
     }
 }
 
+/// Returns true for dead keys and the compose key, which start a compose sequence instead of
+/// producing a character.
+fn is_compose_key(keysym: Keysym) -> bool {
+    keysym == Keysym::Multi_key
+        || (Keysym::dead_grave.raw()..=Keysym::dead_longsolidusoverlay.raw())
+            .contains(&keysym.raw())
+}
+
+/// Converts a dead keysym to the spacing form of its accent.
+fn convert_dead_key(keysym: Keysym) -> Option<char> {
+    match keysym {
+        Keysym::dead_grave => Some('`'),
+        Keysym::dead_acute => Some('´'),
+        Keysym::dead_circumflex => Some('^'),
+        Keysym::dead_tilde => Some('~'),
+        Keysym::dead_macron => Some('¯'),
+        Keysym::dead_breve => Some('˘'),
+        Keysym::dead_abovedot => Some('˙'),
+        Keysym::dead_diaeresis => Some('¨'),
+        Keysym::dead_abovering => Some('˚'),
+        Keysym::dead_doubleacute => Some('˝'),
+        Keysym::dead_caron => Some('ˇ'),
+        Keysym::dead_cedilla => Some('¸'),
+        Keysym::dead_ogonek => Some('˛'),
+        _ => None,
+    }
+}
+
 /// Converts a Smithay Keysym to a Bevy Key.
 fn convert_to_logical_key(keysym: Keysym) -> bevy::input::keyboard::Key {
     // First, attempt to get the character representation based on keyboard layout.
@@ -36,6 +80,14 @@ fn convert_to_logical_key(keysym: Keysym) -> bevy::input::keyboard::Key {
         return Key::Character((c.to_string()).into());
     }
 
+    // Dead keys and the compose key only produce text in combination with the following keys.
+    if keysym == Keysym::Multi_key {
+        return Key::Compose;
+    }
+    if is_compose_key(keysym) {
+        return Key::Dead(convert_dead_key(keysym));
+    }
+
     // If key_char() returned None, it's a non-printable key (Control, Function, Arrow etc.)
     // Map the Keysym variant to the corresponding Bevy Key variant.
     match keysym {
@@ -99,74 +151,10 @@ fn convert_to_logical_key(keysym: Keysym) -> bevy::input::keyboard::Key {
         Keysym::Caps_Lock => Key::CapsLock,
         Keysym::Num_Lock => Key::NumLock,
 
-        // Keys that *might* have produced a character but key_char() returned None.
-        // This could happen for dead keys, IME input, or if key_char() implementation is limited.
-        // We need a fallback. Panicking with todo!() highlights these cases during development.
-        // A production system might need more robust handling (e.g., logging, ignoring, specific Key variant if available).
-        Keysym::A
-        | Keysym::B
-        | Keysym::C
-        | Keysym::D
-        | Keysym::E
-        | Keysym::F
-        | Keysym::G
-        | Keysym::H
-        | Keysym::I
-        | Keysym::J
-        | Keysym::K
-        | Keysym::L
-        | Keysym::M
-        | Keysym::N
-        | Keysym::O
-        | Keysym::P
-        | Keysym::Q
-        | Keysym::R
-        | Keysym::S
-        | Keysym::T
-        | Keysym::U
-        | Keysym::V
-        | Keysym::W
-        | Keysym::X
-        | Keysym::Y
-        | Keysym::Z
-        | Keysym::_0
-        | Keysym::_1
-        | Keysym::_2
-        | Keysym::_3
-        | Keysym::_4
-        | Keysym::_5
-        | Keysym::_6
-        | Keysym::_7
-        | Keysym::_8
-        | Keysym::_9
-        | Keysym::grave
-        | Keysym::minus
-        | Keysym::equal
-        | Keysym::bracketleft
-        | Keysym::bracketright
-        | Keysym::backslash
-        | Keysym::semicolon
-        | Keysym::apostrophe
-        | Keysym::comma
-        | Keysym::period
-        | Keysym::slash
-        | Keysym::space => {
-            warn!(
-                "Keysym {:?} did not produce a character via key_char(). This might be unexpected.",
-                keysym
-            );
-            // Bevy's Key enum doesn't have a generic 'Unidentified' or 'Unknown' variant.
-            // Panicking via todo!() helps catch these during development.
-            // You might need custom logic or decide to ignore these cases in production.
-            todo!(
-                "Unhandled case: Keysym {:?} did not yield a char via key_char(). How to map to bevy::input::keyboard::Key?",
-                keysym
-            )
-        }
-
-        // Catch-all for any other Keysym variants not handled above.
+        // Keys without a counterpart in bevy, e.g. many XF86 media keys.
         _ => {
-            todo!("Unhandled keysym variant: {:?}", keysym)
+            debug!("Unmapped keysym {:?}", keysym);
+            Key::Unidentified(NativeKey::Xkb(keysym.raw()))
         }
     }
 }
@@ -339,24 +327,17 @@ fn convert_to_key_code(keysym: Keysym) -> bevy::prelude::KeyCode {
         Keysym::Pause => bevy::prelude::KeyCode::Pause,
         Keysym::Menu => bevy::prelude::KeyCode::ContextMenu, // Bevy uses ContextMenu
 
-        // --- Fallback ---
-        // This arm catches any Keysym not explicitly handled above.
-        // Using todo!() will cause a panic, alerting you during development
-        // that a key mapping is missing. For production, consider returning Option<KeyCode>
-        // or logging an error instead of panicking.
+        // Keys without a counterpart in bevy, e.g. many XF86 media keys.
         _ => {
-            // e.g., eprintln!("Warning: Unhandled keysym: {:?}", keysym);
-            // If the function returned Option<KeyCode>, you'd return None here.
-            // Since it must return KeyCode, panic is the explicit way to fail.
-            todo!("Unhandled keysym: {:?}", keysym)
-            // Or potentially return a "default" like Escape, but this hides errors:
-            // KeyCode::Escape
+            debug!("Unmapped keysym {:?}", keysym);
+            KeyCode::Unidentified(NativeKeyCode::Xkb(keysym.raw()))
         }
     }
 }
 
+/// The window which currently has keyboard focus.
 #[derive(Resource, Deref)]
-struct ActiveWindow(Entity);
+pub(crate) struct ActiveWindow(pub(crate) Entity);
 impl KeyboardHandler for WaylandState {
     fn enter(
         &mut self,
@@ -374,6 +355,7 @@ impl KeyboardHandler for WaylandState {
             .expect("keyboard event was passed before creating a window!");
         self.world_mut()
             .insert_resource(ActiveWindow(active_window_entity));
+        self.set_window_focus(active_window_entity, true);
     }
 
     fn leave(
@@ -385,16 +367,28 @@ impl KeyboardHandler for WaylandState {
         _serial: u32,
     ) {
         let wayland_surfaces = self.world().non_send_resource::<WaylandSurfaces>();
-        let left_window_entity = *wayland_surfaces
-            .get_window_entity(&surface.id())
-            .expect("keyboard event was passed before creating a window!");
-        let active_window_entity = **self.world().resource::<ActiveWindow>();
-        if left_window_entity != active_window_entity {
+        // The surface may already have been torn down, e.g. lock surfaces after an unlock, in
+        // which case it belonged to the active window only if that was despawned.
+        let Some(&left_window_entity) = wayland_surfaces.get_window_entity(&surface.id()) else {
+            let active_window_despawned = self
+                .world()
+                .get_resource::<ActiveWindow>()
+                .is_some_and(|active_window| self.world().get_entity(**active_window).is_err());
+            if active_window_despawned {
+                self.world_mut().remove_resource::<ActiveWindow>();
+            }
+            return;
+        };
+        let Some(active_window) = self.world().get_resource::<ActiveWindow>() else {
+            return;
+        };
+        if left_window_entity != **active_window {
             // Currently we are assuming that there can only be one window which has access to the
             // keyboard so this check is just redundancy.
             return;
         }
         self.world_mut().remove_resource::<ActiveWindow>();
+        self.set_window_focus(left_window_entity, false);
     }
 
     fn press_key(
@@ -405,13 +399,17 @@ impl KeyboardHandler for WaylandState {
         _serial: u32,
         event: KeyEvent,
     ) {
-        info!("Wayland got a keypress event {:?}", event);
-        let active_window_entity = **self.world().resource::<ActiveWindow>();
+        trace!(?event, "Wayland got a keypress event");
+        // Keys may still arrive after the focused window was closed, until the compositor sends
+        // `leave`.
+        let Some(active_window) = self.world().get_resource::<ActiveWindow>() else {
+            return;
+        };
+        let active_window_entity = **active_window;
+        let time = event.time;
         let keyboard_event =
             convert_keyboard_event(event, active_window_entity, ButtonState::Pressed);
-        self.world_mut().send_event(keyboard_event.clone());
-        self.world_mut()
-            .send_event(WindowEvent::KeyboardInput(keyboard_event));
+        self.send_keyboard_input(time, keyboard_event);
     }
 
     fn repeat_key(
@@ -422,13 +420,15 @@ impl KeyboardHandler for WaylandState {
         _serial: u32,
         event: KeyEvent,
     ) {
-        let active_window_entity = **self.world().resource::<ActiveWindow>();
+        let Some(active_window) = self.world().get_resource::<ActiveWindow>() else {
+            return;
+        };
+        let active_window_entity = **active_window;
+        let time = event.time;
         let mut keyboard_event =
             convert_keyboard_event(event, active_window_entity, ButtonState::Pressed);
         keyboard_event.repeat = true;
-        self.world_mut().send_event(keyboard_event.clone());
-        self.world_mut()
-            .send_event(WindowEvent::KeyboardInput(keyboard_event));
+        self.send_keyboard_input(time, keyboard_event);
     }
 
     fn release_key(
@@ -439,12 +439,14 @@ impl KeyboardHandler for WaylandState {
         _serial: u32,
         event: KeyEvent,
     ) {
-        let active_window_entity = **self.world().resource::<ActiveWindow>();
+        let Some(active_window) = self.world().get_resource::<ActiveWindow>() else {
+            return;
+        };
+        let active_window_entity = **active_window;
+        let time = event.time;
         let keyboard_event =
             convert_keyboard_event(event, active_window_entity, ButtonState::Released);
-        self.world_mut().send_event(keyboard_event.clone());
-        self.world_mut()
-            .send_event(WindowEvent::KeyboardInput(keyboard_event));
+        self.send_keyboard_input(time, keyboard_event);
     }
 
     fn update_modifiers(
@@ -455,8 +457,87 @@ impl KeyboardHandler for WaylandState {
         _serial: u32,
         _modifiers: smithay_client_toolkit::seat::keyboard::Modifiers,
         _raw_modifiers: smithay_client_toolkit::seat::keyboard::RawModifiers,
-        _layout: u32,
+        layout: u32,
     ) {
+        let mut layout_state = self.world_mut().resource_mut::<KeyboardLayoutState>();
+        if layout_state.index == layout {
+            return;
+        }
+        layout_state.index = layout;
+        let name = layout_state.applied.layout_name(layout).map(str::to_owned);
+        self.world_mut().send_event(LayoutChanged {
+            index: layout,
+            name,
+        });
     }
 }
 delegate_keyboard!(WaylandState);
+
+impl WaylandState {
+    fn send_keyboard_input(&mut self, time: u32, keyboard_event: KeyboardInput) {
+        self.world_mut().send_event(keyboard_event.clone());
+        let window = keyboard_event.window;
+        let window_event = WindowEvent::KeyboardInput(keyboard_event);
+        self.send_timestamped_input(time, window, window_event.clone());
+        self.world_mut().send_event(window_event);
+    }
+
+    fn set_window_focus(&mut self, window: Entity, focused: bool) {
+        if let Some(mut bevy_window) = self.world_mut().get_mut::<Window>(window) {
+            bevy_window.focused = focused;
+        }
+        let window_focused = WindowFocused { window, focused };
+        self.world_mut().send_event(window_focused.clone());
+        self.world_mut()
+            .send_event(WindowEvent::WindowFocused(window_focused));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_keys_and_compose_start_sequences() {
+        assert!(is_compose_key(Keysym::Multi_key));
+        assert!(is_compose_key(Keysym::dead_grave));
+        assert!(is_compose_key(Keysym::dead_acute));
+        assert!(is_compose_key(Keysym::dead_longsolidusoverlay));
+        assert!(!is_compose_key(Keysym::a));
+        assert!(!is_compose_key(Keysym::Return));
+    }
+
+    #[test]
+    fn dead_keys_convert_to_spacing_accents() {
+        assert_eq!(convert_dead_key(Keysym::dead_circumflex), Some('^'));
+        assert_eq!(convert_dead_key(Keysym::dead_diaeresis), Some('¨'));
+        assert_eq!(convert_dead_key(Keysym::dead_belowdot), None);
+        assert_eq!(convert_dead_key(Keysym::a), None);
+    }
+
+    #[test]
+    fn unmapped_keysyms_are_unidentified() {
+        let keysym = Keysym::XF86_Eject;
+        assert_eq!(
+            convert_to_logical_key(keysym),
+            Key::Unidentified(NativeKey::Xkb(keysym.raw()))
+        );
+        assert_eq!(
+            convert_to_key_code(keysym),
+            KeyCode::Unidentified(NativeKeyCode::Xkb(keysym.raw()))
+        );
+    }
+
+    #[test]
+    fn compose_keys_convert_to_logical_keys() {
+        assert_eq!(convert_to_logical_key(Keysym::Multi_key), Key::Compose);
+        assert_eq!(
+            convert_to_logical_key(Keysym::dead_grave),
+            Key::Dead(Some('`'))
+        );
+        assert_eq!(
+            convert_to_logical_key(Keysym::dead_belowdot),
+            Key::Dead(None)
+        );
+    }
+}
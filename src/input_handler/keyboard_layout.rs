@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use smithay_client_toolkit::{
+    reexports::client::{
+        protocol::{wl_keyboard::WlKeyboard, wl_seat::WlSeat},
+        Proxy, QueueHandle,
+    },
+    seat::{
+        keyboard::{KeyboardData, RMLVO},
+        SeatState,
+    },
+};
+
+use crate::WaylandState;
+
+/// Defines the xkb keymap used for the keyboard.
+///
+/// When every field is `None` the keymap sent by the compositor is used. Changing this resource at
+/// runtime recreates the keyboard with the new keymap, which needs `wl_seat` version 3.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardLayoutSettings {
+    /// The rules file to use.
+    pub rules: Option<String>,
+    /// The keyboard model by which to interpret keycodes and LEDs.
+    pub model: Option<String>,
+    /// A comma separated list of layouts, e.g. `"us,de"`.
+    pub layout: Option<String>,
+    /// A comma separated list of variants, one per layout.
+    pub variant: Option<String>,
+    /// A comma separated list of xkb options, e.g. `"grp:alt_shift_toggle,compose:ralt"`.
+    pub options: Option<String>,
+}
+impl KeyboardLayoutSettings {
+    fn rmlvo(&self) -> Option<RMLVO> {
+        if *self == Self::default() {
+            return None;
+        }
+        Some(RMLVO {
+            rules: self.rules.clone(),
+            model: self.model.clone(),
+            layout: self.layout.clone(),
+            variant: self.variant.clone(),
+            options: self.options.clone(),
+        })
+    }
+
+    /// Returns the name of the layout at `index` in [`Self::layout`], if it was set.
+    pub fn layout_name(&self, index: u32) -> Option<&str> {
+        self.layout
+            .as_deref()?
+            .split(',')
+            .nth(index as usize)
+            .map(str::trim)
+    }
+}
+
+/// Sent when the keymap was replaced or the active layout of the keymap was switched.
+#[derive(Event, Debug, Clone)]
+pub struct LayoutChanged {
+    /// Index of the active layout within the keymap.
+    pub index: u32,
+    /// Name of the active layout, known only if it was set through [`KeyboardLayoutSettings`].
+    pub name: Option<String>,
+}
+
+/// Keymap state of the current keyboard.
+#[derive(Resource, Default)]
+pub(crate) struct KeyboardLayoutState {
+    /// Settings the current keyboard was created with.
+    pub(crate) applied: KeyboardLayoutSettings,
+    /// Index of the layout which was last reported through [`LayoutChanged`].
+    pub(crate) index: u32,
+}
+
+/// Creates a keyboard for `seat` using the keymap from `settings`, falling back to the
+/// compositor's keymap if the requested one can't be compiled.
+///
+/// Returns whether `settings` were applied, which is `false` after falling back.
+pub(crate) fn create_keyboard(
+    seat_state: &mut SeatState,
+    queue_handle: &QueueHandle<WaylandState>,
+    seat: &WlSeat,
+    settings: &KeyboardLayoutSettings,
+) -> (WlKeyboard, bool) {
+    let rmlvo = settings.rmlvo();
+    // Default settings ask for the compositor's keymap, so they are applied by the fallback too.
+    let default_settings = rmlvo.is_none();
+    if !default_settings {
+        match seat_state.get_keyboard(queue_handle, seat, rmlvo) {
+            Ok(wl_keyboard) => return (wl_keyboard, true),
+            Err(error) => error!("Couldn't apply keyboard layout settings! {:?}", error),
+        }
+    }
+    let wl_keyboard = seat_state
+        .get_keyboard(queue_handle, seat, None)
+        .expect("error while attaching keyboard!");
+    (wl_keyboard, default_settings)
+}
+
+pub(crate) fn update_keyboard_layout(
+    mut commands: Commands,
+    settings: Res<KeyboardLayoutSettings>,
+    wl_keyboard: Option<NonSend<WlKeyboard>>,
+    mut seat_state: NonSendMut<SeatState>,
+    queue_handle: NonSend<QueueHandle<WaylandState>>,
+    mut layout_state: ResMut<KeyboardLayoutState>,
+    mut layout_changed: EventWriter<LayoutChanged>,
+) {
+    if !settings.is_changed() || layout_state.applied == *settings {
+        return;
+    }
+    let Some(wl_keyboard) = wl_keyboard else {
+        return;
+    };
+    let Some(keyboard_data) = wl_keyboard.data::<KeyboardData<WaylandState>>() else {
+        return;
+    };
+    let seat = keyboard_data.seat().clone();
+
+    // Without `release` the old keyboard keeps delivering events next to a new one, so every key
+    // would arrive twice.
+    if wl_keyboard.version() < 3 {
+        warn!(
+            "The seat can't release keyboards, keyboard layout settings apply once it is recreated"
+        );
+        return;
+    }
+    wl_keyboard.release();
+    let (new_keyboard, applied) = create_keyboard(&mut seat_state, &queue_handle, &seat, &settings);
+    commands.queue(move |world: &mut World| world.insert_non_send_resource(new_keyboard));
+    info!("Keyboard layout was changed");
+
+    // The compositor's keymap is used after falling back, the names of its layouts aren't known.
+    layout_state.applied = if applied {
+        settings.clone()
+    } else {
+        KeyboardLayoutSettings::default()
+    };
+    layout_state.index = 0;
+    layout_changed.write(LayoutChanged {
+        index: 0,
+        name: layout_state.applied.layout_name(0).map(str::to_owned),
+    });
+}
@@ -0,0 +1,187 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::WindowEvent,
+};
+use smithay_client_toolkit::reexports::client::protocol::wl_pointer::AxisSource;
+
+use crate::ExternalEventDispatcher;
+
+/// Configures the kinetic scrolling which continues a touchpad scroll after the fingers are lifted.
+#[derive(Resource, Debug, Clone)]
+pub struct KineticScrollSettings {
+    /// Enables kinetic scrolling for finger scrolling on touchpads.
+    pub enabled: bool,
+    /// Exponential decay rate of the scroll velocity, per second.
+    ///
+    /// Higher values stop the scroll sooner.
+    pub friction: f32,
+    /// Velocity in pixels per second below which kinetic scrolling stops.
+    pub min_velocity: f32,
+}
+impl Default for KineticScrollSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            friction: 4.0,
+            min_velocity: 20.0,
+        }
+    }
+}
+
+/// Tracks the velocity of finger scrolling and the fling that follows it.
+#[derive(Resource, Default)]
+pub(crate) struct KineticScroll {
+    window: Option<Entity>,
+    /// Scroll velocity in pixels per second.
+    velocity: Vec2,
+    last_time: Option<u32>,
+    finger_source: bool,
+    flinging: bool,
+}
+impl KineticScroll {
+    /// Feeds an axis event of `window` into the velocity estimate.
+    pub(crate) fn track(
+        &mut self,
+        window: Entity,
+        time: u32,
+        delta: Vec2,
+        source: Option<AxisSource>,
+        stop: bool,
+    ) {
+        // The axis source is only sent with some frames, so the last known source is kept.
+        if let Some(source) = source {
+            self.finger_source = source == AxisSource::Finger;
+        }
+        if !self.finger_source {
+            self.cancel();
+            return;
+        }
+        if stop {
+            self.flinging = self.window.is_some();
+            self.last_time = None;
+            return;
+        }
+
+        if self.window != Some(window) || self.flinging {
+            self.velocity = Vec2::ZERO;
+        }
+        self.window = Some(window);
+        self.flinging = false;
+        if let Some(last_time) = self.last_time {
+            let elapsed = time.wrapping_sub(last_time) as f32 / 1000.0;
+            if elapsed > 0.0 {
+                self.velocity = self.velocity.lerp(delta / elapsed, 0.5);
+            }
+        }
+        self.last_time = Some(time);
+    }
+
+    pub(crate) fn cancel(&mut self) {
+        *self = Self {
+            finger_source: self.finger_source,
+            ..Default::default()
+        };
+    }
+}
+
+pub(crate) fn emit_kinetic_scroll(
+    settings: Res<KineticScrollSettings>,
+    time: Res<Time>,
+    mut kinetic_scroll: ResMut<KineticScroll>,
+    external_event_dispatcher: Res<ExternalEventDispatcher>,
+    mut mouse_wheel_events: EventWriter<MouseWheel>,
+    mut window_events: EventWriter<WindowEvent>,
+) {
+    if !kinetic_scroll.flinging {
+        return;
+    }
+    let Some(window) = kinetic_scroll.window else {
+        return;
+    };
+    let delta_secs = time.delta_secs();
+    kinetic_scroll.velocity *= (-settings.friction * delta_secs).exp();
+    if !settings.enabled || kinetic_scroll.velocity.length() < settings.min_velocity {
+        kinetic_scroll.cancel();
+        return;
+    }
+
+    let scroll = kinetic_scroll.velocity * delta_secs;
+    let mouse_wheel = MouseWheel {
+        unit: MouseScrollUnit::Pixel,
+        x: scroll.x,
+        y: scroll.y,
+        window,
+    };
+    mouse_wheel_events.write(mouse_wheel);
+    window_events.write(mouse_wheel.into());
+
+    // No wayland events arrive while flinging, so the runner has to be woken up for the next frame.
+    let _ = external_event_dispatcher.dispatch();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finger_scroll_flings_after_stop() {
+        let window = Entity::from_raw(1);
+        let mut kinetic_scroll = KineticScroll::default();
+        kinetic_scroll.track(
+            window,
+            0,
+            Vec2::new(0.0, 10.0),
+            Some(AxisSource::Finger),
+            false,
+        );
+        kinetic_scroll.track(window, 10, Vec2::new(0.0, 10.0), None, false);
+        assert_eq!(kinetic_scroll.velocity, Vec2::new(0.0, 500.0));
+        assert!(!kinetic_scroll.flinging);
+
+        kinetic_scroll.track(window, 20, Vec2::ZERO, None, true);
+        assert!(kinetic_scroll.flinging);
+        assert_eq!(kinetic_scroll.window, Some(window));
+    }
+
+    #[test]
+    fn wheel_scroll_cancels_fling() {
+        let window = Entity::from_raw(1);
+        let mut kinetic_scroll = KineticScroll::default();
+        kinetic_scroll.track(
+            window,
+            0,
+            Vec2::new(0.0, 10.0),
+            Some(AxisSource::Finger),
+            false,
+        );
+        kinetic_scroll.track(window, 10, Vec2::new(0.0, 10.0), None, false);
+        kinetic_scroll.track(window, 20, Vec2::ZERO, None, true);
+
+        kinetic_scroll.track(
+            window,
+            30,
+            Vec2::new(0.0, 15.0),
+            Some(AxisSource::Wheel),
+            false,
+        );
+        assert!(!kinetic_scroll.flinging);
+        assert_eq!(kinetic_scroll.window, None);
+        assert_eq!(kinetic_scroll.velocity, Vec2::ZERO);
+    }
+
+    #[test]
+    fn new_window_resets_velocity() {
+        let mut kinetic_scroll = KineticScroll::default();
+        kinetic_scroll.track(
+            Entity::from_raw(1),
+            0,
+            Vec2::Y,
+            Some(AxisSource::Finger),
+            false,
+        );
+        kinetic_scroll.track(Entity::from_raw(1), 10, Vec2::new(0.0, 10.0), None, false);
+        kinetic_scroll.track(Entity::from_raw(2), 20, Vec2::new(0.0, 10.0), None, false);
+        assert_eq!(kinetic_scroll.velocity, Vec2::new(0.0, 500.0));
+    }
+}
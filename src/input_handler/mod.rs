@@ -8,11 +8,21 @@ use smithay_client_toolkit::{
     seat::{Capability, SeatHandler, SeatState},
 };
 
-use crate::WaylandState;
+use crate::{input_latency::TimestampedInput, WaylandState};
 
 mod keyboard;
+mod keyboard_layout;
+mod kinetic_scroll;
 mod pointer;
 
+pub(crate) use keyboard::ActiveWindow;
+use keyboard_layout::{create_keyboard, update_keyboard_layout, KeyboardLayoutState};
+pub use keyboard_layout::{KeyboardLayoutSettings, LayoutChanged};
+pub use kinetic_scroll::KineticScrollSettings;
+use kinetic_scroll::{emit_kinetic_scroll, KineticScroll};
+#[cfg(any(feature = "test_support", feature = "input_recording"))]
+pub(crate) use pointer::{send_window_event, set_cursor_position};
+
 pub struct InputHandlerPlugin;
 impl Plugin for InputHandlerPlugin {
     fn build(&self, app: &mut App) {
@@ -21,6 +31,13 @@ impl Plugin for InputHandlerPlugin {
         let seat_state = SeatState::new(globals, queue_handle);
 
         app.insert_non_send_resource(seat_state);
+        app.init_resource::<KeyboardLayoutSettings>();
+        app.init_resource::<KeyboardLayoutState>();
+        app.add_event::<LayoutChanged>();
+        app.add_event::<TimestampedInput>();
+        app.init_resource::<KineticScrollSettings>();
+        app.init_resource::<KineticScroll>();
+        app.add_systems(PreUpdate, (update_keyboard_layout, emit_kinetic_scroll));
     }
 }
 
@@ -47,11 +64,18 @@ impl SeatHandler for WaylandState {
         capability: smithay_client_toolkit::seat::Capability,
     ) {
         if capability == Capability::Keyboard {
+            let settings = self.world().resource::<KeyboardLayoutSettings>().clone();
             let mut seat_state = self.world_mut().non_send_resource_mut::<SeatState>();
-            let wl_keyboard = seat_state
-                .get_keyboard(qh, &seat, None)
-                .expect("error while attaching keyboard!");
+            let (wl_keyboard, applied) = create_keyboard(&mut seat_state, qh, &seat, &settings);
             self.world_mut().insert_non_send_resource(wl_keyboard);
+            self.world_mut().insert_resource(KeyboardLayoutState {
+                applied: if applied {
+                    settings
+                } else {
+                    KeyboardLayoutSettings::default()
+                },
+                index: 0,
+            });
             info!("Keyboard Attached");
         }
         if capability == Capability::Pointer {
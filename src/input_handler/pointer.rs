@@ -3,13 +3,15 @@ use bevy::{
         mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel},
         ButtonState,
     },
-    prelude::MouseButton,
+    math::Vec2,
+    prelude::{Entity, MouseButton, World},
     window::{CursorEntered, CursorLeft, CursorMoved, Window, WindowEvent},
 };
 use smithay_client_toolkit::{
     delegate_pointer, reexports::client::Proxy, seat::pointer::PointerHandler,
 };
 
+use super::kinetic_scroll::KineticScroll;
 use crate::{surface_handler::WaylandSurfaces, WaylandState};
 
 /// Converts a u32 button code to a Bevy MouseButton.
@@ -42,86 +44,145 @@ impl PointerHandler for WaylandState {
             let entity = *entity.unwrap();
 
             let window = self.world().get::<Window>(entity).unwrap().clone();
-            let mut position = bevy::math::Vec2 {
+            // Wayland reports surface-local coordinates, which are already logical pixels.
+            let position = Vec2 {
                 x: event.position.0 as f32,
                 y: event.position.1 as f32,
             };
             let delta = window
-                .physical_cursor_position()
-                .map(|old_position| (position - old_position) / window.scale_factor());
-            let pointer_event: WindowEvent = match event.kind {
+                .cursor_position()
+                .map(|old_position| position - old_position);
+            let cursor_moved = CursorMoved {
+                window: entity,
+                position,
+                delta,
+            };
+            let time = match event.kind {
+                smithay_client_toolkit::seat::pointer::PointerEventKind::Motion { time }
+                | smithay_client_toolkit::seat::pointer::PointerEventKind::Press { time, .. }
+                | smithay_client_toolkit::seat::pointer::PointerEventKind::Release {
+                    time, ..
+                }
+                | smithay_client_toolkit::seat::pointer::PointerEventKind::Axis { time, .. } => {
+                    Some(time)
+                }
+                _ => None,
+            };
+            let pointer_events: Vec<WindowEvent> = match event.kind {
                 smithay_client_toolkit::seat::pointer::PointerEventKind::Enter { .. } => {
-                    CursorEntered { window: entity }.into()
+                    self.set_cursor_position(entity, Some(position * window.scale_factor()));
+                    vec![CursorEntered { window: entity }.into(), cursor_moved.into()]
                 }
                 smithay_client_toolkit::seat::pointer::PointerEventKind::Leave { .. } => {
-                    CursorLeft { window: entity }.into()
+                    // Clearing the position ends hover interactions on the surface that was left.
+                    self.set_cursor_position(entity, None);
+                    vec![CursorLeft { window: entity }.into()]
                 }
                 smithay_client_toolkit::seat::pointer::PointerEventKind::Motion { .. } => {
-                    self.world_mut()
-                        .get_mut::<Window>(entity)
-                        .unwrap()
-                        .set_physical_cursor_position(Some(position.as_dvec2()));
-                    position /= window.scale_factor();
-                    CursorMoved {
-                        window: entity,
-                        position,
-                        delta,
-                    }
-                    .into()
+                    self.set_cursor_position(entity, Some(position * window.scale_factor()));
+                    vec![cursor_moved.into()]
                 }
                 smithay_client_toolkit::seat::pointer::PointerEventKind::Press {
                     button, ..
-                } => MouseButtonInput {
-                    button: convert_to_mouse_button(button),
-                    state: ButtonState::Pressed,
-                    window: entity,
+                } => {
+                    self.world_mut().resource_mut::<KineticScroll>().cancel();
+                    vec![MouseButtonInput {
+                        button: convert_to_mouse_button(button),
+                        state: ButtonState::Pressed,
+                        window: entity,
+                    }
+                    .into()]
                 }
-                .into(),
 
                 smithay_client_toolkit::seat::pointer::PointerEventKind::Release {
                     button, ..
-                } => MouseButtonInput {
+                } => vec![MouseButtonInput {
                     button: convert_to_mouse_button(button),
                     state: ButtonState::Released,
                     window: entity,
                 }
-                .into(),
+                .into()],
                 smithay_client_toolkit::seat::pointer::PointerEventKind::Axis {
+                    time,
                     horizontal,
                     vertical,
-                    ..
-                } => MouseWheel {
-                    unit: MouseScrollUnit::Pixel,
-                    x: horizontal.absolute as f32,
-                    y: vertical.absolute as f32,
-                    window: entity,
+                    source,
+                } => {
+                    self.world_mut().resource_mut::<KineticScroll>().track(
+                        entity,
+                        time,
+                        Vec2::new(horizontal.absolute as f32, vertical.absolute as f32),
+                        source,
+                        horizontal.stop || vertical.stop,
+                    );
+                    vec![MouseWheel {
+                        unit: MouseScrollUnit::Pixel,
+                        x: horizontal.absolute as f32,
+                        y: vertical.absolute as f32,
+                        window: entity,
+                    }
+                    .into()]
                 }
-                .into(),
             };
-            let window_event: WindowEvent = pointer_event;
-            match window_event.clone() {
-                WindowEvent::CursorEntered(e) => {
-                    self.world_mut().send_event(e);
-                }
-                WindowEvent::CursorLeft(e) => {
-                    self.world_mut().send_event(e);
-                }
-                WindowEvent::CursorMoved(e) => {
-                    self.world_mut().send_event(e);
+            for pointer_event in pointer_events {
+                if let Some(time) = time {
+                    self.send_timestamped_input(time, entity, pointer_event.clone());
                 }
-                WindowEvent::MouseButtonInput(e) => {
-                    self.world_mut().send_event(e);
-                }
-                WindowEvent::MouseMotion(e) => {
-                    self.world_mut().send_event(e);
-                }
-                WindowEvent::MouseWheel(e) => {
-                    self.world_mut().send_event(e);
-                }
-                _ => {}
+                self.send_window_event(pointer_event);
             }
-            self.world_mut().send_event::<WindowEvent>(window_event);
         }
     }
 }
 delegate_pointer!(WaylandState);
+
+impl WaylandState {
+    fn set_cursor_position(&mut self, entity: Entity, physical_position: Option<Vec2>) {
+        set_cursor_position(self.world_mut(), entity, physical_position);
+    }
+
+    fn send_window_event(&mut self, window_event: WindowEvent) {
+        send_window_event(self.world_mut(), window_event);
+    }
+}
+
+pub(crate) fn set_cursor_position(
+    world: &mut World,
+    entity: Entity,
+    physical_position: Option<Vec2>,
+) {
+    if let Some(mut window) = world.get_mut::<Window>(entity) {
+        window.set_physical_cursor_position(physical_position.map(|position| position.as_dvec2()));
+    }
+}
+
+/// Sends `window_event` both as itself and as the input event it wraps.
+pub(crate) fn send_window_event(world: &mut World, window_event: WindowEvent) {
+    match window_event.clone() {
+        WindowEvent::CursorEntered(e) => {
+            world.send_event(e);
+        }
+        WindowEvent::CursorLeft(e) => {
+            world.send_event(e);
+        }
+        WindowEvent::CursorMoved(e) => {
+            world.send_event(e);
+        }
+        WindowEvent::MouseButtonInput(e) => {
+            world.send_event(e);
+        }
+        WindowEvent::MouseMotion(e) => {
+            world.send_event(e);
+        }
+        WindowEvent::MouseWheel(e) => {
+            world.send_event(e);
+        }
+        WindowEvent::KeyboardInput(e) => {
+            world.send_event(e);
+        }
+        WindowEvent::TouchInput(e) => {
+            world.send_event(e);
+        }
+        _ => {}
+    }
+    world.send_event::<WindowEvent>(window_event);
+}
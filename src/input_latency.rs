@@ -0,0 +1,189 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    ecs::entity::EntityHashMap,
+    prelude::*,
+    window::WindowEvent,
+};
+use rustix::time::ClockId;
+use smithay_client_toolkit::reexports::{
+    client::{globals::GlobalList, Connection, Dispatch, QueueHandle},
+    protocols::wp::presentation_time::client::{
+        wp_presentation::{self, WpPresentation},
+        wp_presentation_feedback::{self, WpPresentationFeedback},
+    },
+};
+
+use crate::{error::is_connected, surface_handler::WaylandSurfaces, WaylandState};
+
+/// An input event together with the time the compositor sent it at.
+///
+/// Sent in addition to the regular bevy input event for every pointer and keyboard event which
+/// carries a timestamp.
+#[derive(Event, Debug, Clone)]
+pub struct TimestampedInput {
+    /// Timestamp of the wayland event in milliseconds.
+    ///
+    /// The base is undefined by the protocol, but compositors use `CLOCK_MONOTONIC`.
+    pub time_msec: u32,
+    /// The window which received the input.
+    pub window: Entity,
+    pub event: WindowEvent,
+}
+
+/// Measures the time from the compositor sending an input event until it presented the first frame
+/// of the window after it, using `wp_presentation` feedback.
+///
+/// The feedback is requested for the next commit of every window which received input in a frame.
+/// With pipelined rendering that commit may still belong to the previous frame, so the measured
+/// latency can be up to one frame too low.
+///
+/// The highest latency presented since the previous frame is recorded under
+/// [`Self::INPUT_LATENCY`] in milliseconds. Nothing is measured if the compositor doesn't support
+/// `wp_presentation` or its clock isn't `CLOCK_MONOTONIC`, which the input timestamps use.
+pub struct InputLatencyDiagnosticsPlugin;
+impl InputLatencyDiagnosticsPlugin {
+    pub const INPUT_LATENCY: DiagnosticPath = DiagnosticPath::const_new("wayland/input_latency");
+}
+impl Plugin for InputLatencyDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::INPUT_LATENCY).with_suffix("ms"));
+        if !is_connected(app) {
+            return;
+        }
+        let globals = app.world().non_send_resource::<GlobalList>();
+        let queue_handle = app.world().non_send_resource::<QueueHandle<WaylandState>>();
+        let presentation = match globals.bind::<WpPresentation, _, _>(queue_handle, 1..=1, ()) {
+            Ok(presentation) => presentation,
+            Err(error) => {
+                warn!(
+                    "Couldn't bind presentation time, input latency isn't measured! {:?}",
+                    error
+                );
+                return;
+            }
+        };
+
+        app.insert_non_send_resource(presentation)
+            .init_resource::<PresentationClock>()
+            .init_resource::<PresentedInputLatencies>()
+            .add_systems(First, record_input_latencies)
+            .add_systems(Last, request_presentation_feedback);
+    }
+}
+
+/// Latencies above this are assumed to come from a compositor which doesn't use `CLOCK_MONOTONIC`
+/// for input timestamps.
+const MAX_PLAUSIBLE_LATENCY_MSEC: u32 = 10_000;
+
+/// Clock of the presentation timestamps, sent by the compositor after binding `wp_presentation`.
+#[derive(Resource, Default, Deref, DerefMut)]
+struct PresentationClock(Option<u32>);
+
+/// Latencies of the frames presented since the last update, in milliseconds.
+#[derive(Resource, Default, Deref, DerefMut)]
+struct PresentedInputLatencies(Vec<u32>);
+
+/// Timestamp of the oldest input a presentation feedback was requested for.
+struct InputFeedback {
+    time_msec: u32,
+}
+
+fn request_presentation_feedback(
+    mut timestamped_inputs: EventReader<TimestampedInput>,
+    presentation: NonSend<WpPresentation>,
+    wayland_surfaces: NonSend<WaylandSurfaces>,
+    queue_handle: NonSend<QueueHandle<WaylandState>>,
+) {
+    // Events are read in the order they were sent, so the first input of each window is the oldest.
+    let mut oldest_inputs = EntityHashMap::<u32>::default();
+    for timestamped_input in timestamped_inputs.read() {
+        oldest_inputs
+            .entry(timestamped_input.window)
+            .or_insert(timestamped_input.time_msec);
+    }
+    for (window, time_msec) in oldest_inputs {
+        let Some(window_wrapper) = wayland_surfaces.get_window_wrapper(window) else {
+            continue;
+        };
+        presentation.feedback(
+            window_wrapper.wl_surface(),
+            &queue_handle,
+            InputFeedback { time_msec },
+        );
+    }
+}
+
+fn record_input_latencies(
+    mut presented_input_latencies: ResMut<PresentedInputLatencies>,
+    mut diagnostics: Diagnostics,
+) {
+    if let Some(latency) = presented_input_latencies.drain(..).max() {
+        diagnostics.add_measurement(&InputLatencyDiagnosticsPlugin::INPUT_LATENCY, || {
+            latency as f64
+        });
+    }
+}
+
+impl Dispatch<WpPresentation, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WpPresentation,
+        event: wp_presentation::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wp_presentation::Event::ClockId { clk_id } = event {
+            **state.world_mut().resource_mut::<PresentationClock>() = Some(clk_id);
+        }
+    }
+}
+
+impl Dispatch<WpPresentationFeedback, InputFeedback> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &WpPresentationFeedback,
+        event: wp_presentation_feedback::Event,
+        data: &InputFeedback,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // Discarded frames were never shown, the input is measured with a later feedback.
+        let wp_presentation_feedback::Event::Presented {
+            tv_sec_hi,
+            tv_sec_lo,
+            tv_nsec,
+            ..
+        } = event
+        else {
+            return;
+        };
+        if **state.world().resource::<PresentationClock>() != Some(ClockId::Monotonic as u32) {
+            return;
+        }
+        let tv_sec = (u64::from(tv_sec_hi) << 32) | u64::from(tv_sec_lo);
+        let presented_msec = (tv_sec * 1000 + u64::from(tv_nsec) / 1_000_000) as u32;
+        let latency = presented_msec.wrapping_sub(data.time_msec);
+        if latency <= MAX_PLAUSIBLE_LATENCY_MSEC {
+            state
+                .world_mut()
+                .resource_mut::<PresentedInputLatencies>()
+                .push(latency);
+        }
+    }
+}
+
+impl WaylandState {
+    pub(crate) fn send_timestamped_input(
+        &mut self,
+        time_msec: u32,
+        window: Entity,
+        event: WindowEvent,
+    ) {
+        self.world_mut().send_event(TimestampedInput {
+            time_msec,
+            window,
+            event,
+        });
+    }
+}
@@ -0,0 +1,205 @@
+//! Records input to a file and plays it back, e.g. to reproduce bugs reported from the field.
+//!
+//! Every input [`WindowEvent`] is written as one line of RON together with the time of the frame
+//! which handled it, relative to the first recorded event. Playback sends the events through the
+//! same path as the ones converted from wayland events, keeping their order and relative timing.
+//!
+//! Events refer to windows by entity, so the app has to spawn its windows in the same order as in
+//! the recorded session.
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::Instant,
+};
+
+use bevy::{input::InputSystem, prelude::*, window::WindowEvent};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input_handler::{send_window_event, set_cursor_position},
+    ExternalEventDispatcher,
+};
+
+/// One line of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedInput {
+    /// Milliseconds since the first recorded event.
+    pub time_msec: u32,
+    pub event: WindowEvent,
+}
+
+/// Writes all pointer, keyboard and touch input to `path`, replacing the file if it exists.
+///
+/// The file is flushed every frame, so the recording survives a crash of the app.
+pub struct InputRecordingPlugin {
+    pub path: PathBuf,
+}
+impl Plugin for InputRecordingPlugin {
+    fn build(&self, app: &mut App) {
+        let file = match File::create(&self.path) {
+            Ok(file) => file,
+            Err(error) => {
+                error!(
+                    "Unable to create input recording {:?}: {}",
+                    self.path, error
+                );
+                return;
+            }
+        };
+        app.insert_resource(InputRecorder {
+            writer: BufWriter::new(file),
+            start: None,
+        })
+        .add_systems(Last, record_input);
+    }
+}
+
+#[derive(Resource)]
+struct InputRecorder {
+    writer: BufWriter<File>,
+    start: Option<Instant>,
+}
+
+fn is_input(event: &WindowEvent) -> bool {
+    matches!(
+        event,
+        WindowEvent::CursorEntered(_)
+            | WindowEvent::CursorLeft(_)
+            | WindowEvent::CursorMoved(_)
+            | WindowEvent::MouseButtonInput(_)
+            | WindowEvent::MouseMotion(_)
+            | WindowEvent::MouseWheel(_)
+            | WindowEvent::KeyboardInput(_)
+            | WindowEvent::TouchInput(_)
+    )
+}
+
+fn record_input(
+    mut window_events: EventReader<WindowEvent>,
+    mut input_recorder: ResMut<InputRecorder>,
+) {
+    let mut input_events = window_events
+        .read()
+        .filter(|event| is_input(event))
+        .peekable();
+    if input_events.peek().is_none() {
+        return;
+    }
+    let time_msec = input_recorder
+        .start
+        .get_or_insert_with(Instant::now)
+        .elapsed()
+        .as_millis() as u32;
+    for event in input_events {
+        let recorded_input = RecordedInput {
+            time_msec,
+            event: event.clone(),
+        };
+        let line = ron::to_string(&recorded_input).expect("Unable to serialize input");
+        if let Err(error) = writeln!(input_recorder.writer, "{line}") {
+            error!("Unable to write input recording: {}", error);
+            return;
+        }
+    }
+    if let Err(error) = input_recorder.writer.flush() {
+        error!("Unable to write input recording: {}", error);
+    }
+}
+
+/// Plays back a recording made with [`InputRecordingPlugin`] from `path`.
+///
+/// Playback starts with the first frame and [`InputPlaybackFinished`] is sent after the last event.
+/// Real input is still handled during playback.
+pub struct InputPlaybackPlugin {
+    pub path: PathBuf,
+}
+impl Plugin for InputPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        let recording = match std::fs::read_to_string(&self.path) {
+            Ok(recording) => recording,
+            Err(error) => {
+                error!("Unable to read input recording {:?}: {}", self.path, error);
+                return;
+            }
+        };
+        let recorded_inputs = recording
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(ron::from_str::<RecordedInput>)
+            .collect::<Result<VecDeque<_>, _>>();
+        let recorded_inputs = match recorded_inputs {
+            Ok(recorded_inputs) => recorded_inputs,
+            Err(error) => {
+                error!("Invalid input recording {:?}: {}", self.path, error);
+                return;
+            }
+        };
+
+        app.insert_resource(InputPlayback {
+            recorded_inputs,
+            start: None,
+        })
+        .add_event::<InputPlaybackFinished>()
+        .add_systems(PreUpdate, play_back_input.before(InputSystem));
+    }
+}
+
+/// Sent once every event of the recording was played back.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct InputPlaybackFinished;
+
+#[derive(Resource)]
+struct InputPlayback {
+    recorded_inputs: VecDeque<RecordedInput>,
+    start: Option<Instant>,
+}
+
+fn play_back_input(world: &mut World) {
+    let mut input_playback = world.resource_mut::<InputPlayback>();
+    if input_playback.recorded_inputs.is_empty() {
+        return;
+    }
+    let elapsed_msec = input_playback
+        .start
+        .get_or_insert_with(Instant::now)
+        .elapsed()
+        .as_millis();
+
+    let mut due_events = Vec::new();
+    while input_playback
+        .recorded_inputs
+        .front()
+        .is_some_and(|recorded_input| recorded_input.time_msec as u128 <= elapsed_msec)
+    {
+        let recorded_input = input_playback.recorded_inputs.pop_front().unwrap();
+        due_events.push(recorded_input.event);
+    }
+    let finished = input_playback.recorded_inputs.is_empty();
+
+    for event in due_events {
+        match &event {
+            WindowEvent::CursorMoved(cursor_moved) => {
+                let Some(window) = world.get::<Window>(cursor_moved.window) else {
+                    warn!("Recorded input refers to a window that doesn't exist");
+                    continue;
+                };
+                let physical_position = cursor_moved.position * window.scale_factor();
+                set_cursor_position(world, cursor_moved.window, Some(physical_position));
+            }
+            WindowEvent::CursorLeft(cursor_left) => {
+                set_cursor_position(world, cursor_left.window, None);
+            }
+            _ => {}
+        }
+        send_window_event(world, event);
+    }
+    if finished {
+        world.send_event(InputPlaybackFinished);
+    } else if let Some(external_event_dispatcher) = world.get_resource::<ExternalEventDispatcher>()
+    {
+        // Keeps the runner updating while events are pending, even if the compositor sends none.
+        let _ = external_event_dispatcher.dispatch();
+    }
+}
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use crate::{
+    error::is_connected,
+    input_region::InputRegion,
+    layer_shell::{LayerShellKeyboardInteractivity, LayerShellSettings},
+    surface_handler::create_windows,
+};
+
+/// Present while the app runs in kiosk mode, see [`KioskModePlugin`].
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct KioskMode;
+
+/// Marks the window shown in kiosk mode, see [`KioskModePlugin`].
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct KioskWindow;
+
+/// Runs the app as a dedicated appliance.
+///
+/// The window marked with [`KioskWindow`] is pinned as a fullscreen layer surface on the overlay
+/// layer grabbing the keyboard and receiving all pointer input, every other window of the app is
+/// kept hidden and [`SessionLockEvent::Lock`](crate::session_lock::SessionLockEvent::Lock) is
+/// ignored. Surfaces of other clients are covered, but overlay surfaces mapped later can still be
+/// shown above it.
+///
+/// Not part of [`WaylandPlugins`](crate::WaylandPlugins), add it next to them.
+pub struct KioskModePlugin;
+impl Plugin for KioskModePlugin {
+    fn build(&self, app: &mut App) {
+        if !is_connected(app) {
+            return;
+        }
+        app.init_resource::<KioskMode>()
+            .add_systems(PreUpdate, pin_kiosk_window.before(create_windows))
+            .add_systems(Update, hide_other_windows);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn pin_kiosk_window(
+    mut commands: Commands,
+    mut kiosk_windows: Query<
+        (
+            Entity,
+            Option<&mut LayerShellSettings>,
+            Has<InputRegion>,
+            Has<LayerShellKeyboardInteractivity>,
+        ),
+        (With<Window>, With<KioskWindow>),
+    >,
+) {
+    for (entity, layer_shell_settings, input_region, keyboard_interactivity) in &mut kiosk_windows {
+        let fullscreen_overlay = LayerShellSettings::fullscreen_overlay();
+        match layer_shell_settings {
+            Some(mut layer_shell_settings) => {
+                layer_shell_settings.set_if_neq(fullscreen_overlay);
+            }
+            None => {
+                commands.entity(entity).insert(fullscreen_overlay);
+            }
+        }
+        // Both would let input or keyboard focus through to other surfaces.
+        if input_region || keyboard_interactivity {
+            commands
+                .entity(entity)
+                .remove::<(InputRegion, LayerShellKeyboardInteractivity)>();
+        }
+    }
+}
+
+fn hide_other_windows(
+    kiosk_windows: Query<(), (With<Window>, With<KioskWindow>)>,
+    mut windows: Query<&mut Window, Without<KioskWindow>>,
+    mut missing_reported: Local<bool>,
+) {
+    // Hiding everything without a kiosk window would leave a blank screen.
+    if kiosk_windows.is_empty() {
+        if !*missing_reported && !windows.is_empty() {
+            error!("Kiosk mode is enabled but no window is marked with KioskWindow");
+            *missing_reported = true;
+        }
+        return;
+    }
+    *missing_reported = false;
+    for mut window in &mut windows {
+        if window.visible {
+            warn!("Windows other than the kiosk window are hidden in kiosk mode");
+            window.visible = false;
+        }
+    }
+}
@@ -1,7 +1,13 @@
-use bevy::{platform::collections::HashMap, prelude::*};
+use bevy::{
+    platform::collections::HashMap,
+    prelude::*,
+    window::{WindowCloseRequested, WindowEvent, WindowFocused, WindowResized},
+};
 use smithay_client_toolkit::{
     delegate_layer,
-    reexports::client::{globals::GlobalList, QueueHandle},
+    reexports::client::{
+        backend::ObjectId, globals::GlobalList, protocol::wl_output::WlOutput, Proxy, QueueHandle,
+    },
     shell::{
         wlr_layer::{
             Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
@@ -11,17 +17,30 @@ use smithay_client_toolkit::{
 };
 
 use crate::{
-    surface_handler::{create_windows, SurfaceConfigured, WaylandSurfaces},
+    capabilities::WaylandCapabilities,
+    error::{is_connected, report_error, WaylandError},
+    input_handler::ActiveWindow,
+    surface_handler::{create_windows, DeferredDrop, SurfaceConfigured, WaylandSurfaces},
     WaylandState,
 };
 
 #[derive(Default, Deref, DerefMut)]
-struct LayerShellWindows(HashMap<Entity, LayerShellWindow>);
+pub(crate) struct LayerShellWindows(HashMap<Entity, LayerShellWindow>);
+impl LayerShellWindows {
+    /// Re-issues the keyboard interactivity of the layer surface so that the compositor hands
+    /// keyboard focus back to it.
+    pub(crate) fn layer_surfaces(&self) -> impl Iterator<Item = &LayerSurface> {
+        self.values()
+            .map(|layer_shell_window| &layer_shell_window.layer_surface)
+    }
+}
 
-struct LayerShellWindow {
+pub(crate) struct LayerShellWindow {
     layer_surface: LayerSurface,
     layer_shell_settings: LayerShellSettings,
     window_size: (u32, u32),
+    focus_requested: bool,
+    keyboard_interactivity: Option<KeyboardInteractivity>,
 }
 impl LayerShellWindow {
     fn new(
@@ -33,18 +52,25 @@ impl LayerShellWindow {
             layer_surface,
             layer_shell_settings,
             window_size,
+            focus_requested: false,
+            keyboard_interactivity: None,
         };
         layer_shell_window.sync();
         layer_shell_window
     }
 
     fn sync(&mut self) {
+        let _span = debug_span!(
+            "layer_surface_commit",
+            surface = %self.layer_surface.wl_surface().id()
+        )
+        .entered();
         self.layer_surface
             .set_layer(self.layer_shell_settings.layer);
         self.layer_surface
             .set_anchor(self.layer_shell_settings.anchor);
         self.layer_surface
-            .set_keyboard_interactivity(self.layer_shell_settings.keyboard_interactivity);
+            .set_keyboard_interactivity(self.keyboard_interactivity());
         self.layer_surface
             .set_exclusive_zone(self.layer_shell_settings.exclusive_zone);
 
@@ -60,6 +86,41 @@ impl LayerShellWindow {
         self.layer_surface.commit();
     }
 
+    /// Returns the keyboard interactivity from [`LayerShellKeyboardInteractivity`] if present,
+    /// otherwise the one from the settings.
+    fn keyboard_interactivity(&self) -> KeyboardInteractivity {
+        self.keyboard_interactivity
+            .unwrap_or(self.layer_shell_settings.keyboard_interactivity)
+    }
+
+    fn set_keyboard_interactivity(
+        &mut self,
+        keyboard_interactivity: Option<KeyboardInteractivity>,
+    ) {
+        if self.keyboard_interactivity == keyboard_interactivity {
+            return;
+        }
+        self.keyboard_interactivity = keyboard_interactivity;
+        // A pending focus request restores the interactivity once the focus is gone.
+        if !self.focus_requested {
+            self.layer_surface
+                .set_keyboard_interactivity(self.keyboard_interactivity());
+            self.layer_surface.commit();
+        }
+    }
+
+    /// Requests a new size if the size is inherited from the window.
+    fn set_window_size(&mut self, window_size: (u32, u32)) {
+        if self.window_size == window_size {
+            return;
+        }
+        self.window_size = window_size;
+        if self.layer_shell_settings.size == LayerShellWindowSize::Inherit {
+            self.layer_surface.set_size(window_size.0, window_size.1);
+            self.layer_surface.commit();
+        }
+    }
+
     pub fn set_settings(&mut self, layer_shell_settings: LayerShellSettings) {
         if self.layer_shell_settings == layer_shell_settings {
             return;
@@ -69,6 +130,53 @@ impl LayerShellWindow {
     }
 }
 
+/// Overrides [`LayerShellSettings::keyboard_interactivity`] of a layer shell window.
+///
+/// Changes to this component are applied to the layer surface right away, e.g. to make a status bar
+/// `OnDemand` only while its dropdown is open. Removing it restores the value from the settings.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Deref, DerefMut)]
+pub struct LayerShellKeyboardInteractivity(pub KeyboardInteractivity);
+
+/// Places a layer shell window on a specific output instead of the one picked by the compositor.
+#[derive(Component)]
+pub(crate) struct LayerShellOutput(pub(crate) WlOutput);
+
+/// Sent when the compositor configures the size of a layer shell window.
+///
+/// A `width` or `height` of 0 leaves that dimension to the app. Otherwise the window is resized to
+/// the suggested size, e.g. to the width of the output for a surface anchored to the left and right
+/// edges with a [`LayerShellWindowSize::Fixed`] width of 0.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LayerShellConfigure {
+    pub window: Entity,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Offsets the margins of a layer shell window by the exclusive zones of the other layer shell
+/// windows of this app that are anchored to the same edges of the same output, on the same layer
+/// or a higher one.
+///
+/// This allows placing e.g. a notification drawer below a status bar without hardcoding the height
+/// of the bar in [`LayerShellSettings::margin`], which is still added on top of the offset. The
+/// drawer may be on a lower layer than the bar, but not on a higher one, since the compositor
+/// arranges each layer around the exclusive zones of its own and all higher layers.
+///
+/// Only applies to windows with a negative [`LayerShellSettings::exclusive_zone`]. The compositor
+/// already moves windows with an exclusive zone of 0 out of the exclusive zones of others.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct LayerShellAutoMargin;
+
+/// Asks the compositor to give keyboard focus to a layer shell window.
+///
+/// The surface is temporarily made `Exclusive` until it gains focus and then kept `OnDemand`, or
+/// `Exclusive` if configured so, until it loses focus again, after which its configured keyboard
+/// interactivity applies.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RequestFocus {
+    pub window: Entity,
+}
+
 #[derive(Default, Eq, PartialEq, Clone, Debug)]
 pub enum LayerShellWindowSize {
     #[default]
@@ -105,6 +213,40 @@ pub struct LayerShellSettings {
     /// always drawn on top of surfaces on lower layers.
     pub layer: Layer,
 }
+impl LayerShellSettings {
+    /// Settings covering the whole output above everything else while grabbing the keyboard, used
+    /// by the session lock fallback and kiosk mode.
+    pub(crate) fn fullscreen_overlay() -> Self {
+        Self {
+            anchor: Anchor::all(),
+            size: LayerShellWindowSize::Fixed(0, 0),
+            exclusive_zone: -1,
+            keyboard_interactivity: KeyboardInteractivity::Exclusive,
+            layer: Layer::Overlay,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the edge on which the exclusive zone of the surface is reserved.
+    ///
+    /// The exclusive zone only applies to surfaces anchored to a single edge, optionally stretched
+    /// along it by also anchoring to both perpendicular edges.
+    fn exclusive_edge(&self) -> Option<Anchor> {
+        if self.exclusive_zone <= 0 {
+            return None;
+        }
+        [Anchor::TOP, Anchor::BOTTOM, Anchor::LEFT, Anchor::RIGHT]
+            .into_iter()
+            .find(|&edge| {
+                let perpendicular = if edge.intersects(Anchor::TOP | Anchor::BOTTOM) {
+                    Anchor::LEFT | Anchor::RIGHT
+                } else {
+                    Anchor::TOP | Anchor::BOTTOM
+                };
+                self.anchor == edge || self.anchor == edge | perpendicular
+            })
+    }
+}
 impl Default for LayerShellSettings {
     fn default() -> Self {
         Self {
@@ -121,8 +263,43 @@ impl Default for LayerShellSettings {
 pub struct LayerShellPlugin;
 impl Plugin for LayerShellPlugin {
     fn build(&self, app: &mut App) {
+        if !is_connected(app) {
+            return;
+        }
+        let globals = app.world().non_send_resource::<GlobalList>();
+        let queue_handle = app.world().non_send_resource::<QueueHandle<WaylandState>>();
+        match LayerShell::bind(globals, queue_handle) {
+            Ok(layer_shell) => {
+                app.insert_non_send_resource(layer_shell);
+                app.world_mut()
+                    .resource_mut::<WaylandCapabilities>()
+                    .layer_shell = true;
+            }
+            Err(error) => {
+                report_error(
+                    app,
+                    WaylandError::MissingGlobal {
+                        interface: "zwlr_layer_shell_v1",
+                        error,
+                    },
+                );
+                return;
+            }
+        }
+
         app.add_systems(PreUpdate, assign_layer_shell_role.after(create_windows))
-            .add_systems(Update, update_layer_shell_settings)
+            .add_systems(Last, release_layer_surfaces)
+            .add_systems(
+                Update,
+                (
+                    update_layer_shell_settings,
+                    update_keyboard_interactivity.after(update_layer_shell_settings),
+                    handle_focus_requests,
+                    release_focus_requests,
+                ),
+            )
+            .add_event::<RequestFocus>()
+            .add_event::<LayerShellConfigure>()
             .insert_non_send_resource(LayerShellWindows::default());
     }
 }
@@ -131,24 +308,30 @@ fn assign_layer_shell_role(
     mut commands: Commands,
     wayland_surfaces: NonSend<WaylandSurfaces>,
     queue_handle: NonSend<QueueHandle<WaylandState>>,
-    globals: NonSend<GlobalList>,
-    windows: Query<(Entity, &Window, &LayerShellSettings), Without<SurfaceConfigured>>,
+    layer_shell: NonSend<LayerShell>,
+    windows: Query<
+        (
+            Entity,
+            &Window,
+            &LayerShellSettings,
+            Option<&LayerShellOutput>,
+        ),
+        Without<SurfaceConfigured>,
+    >,
     mut layer_shell_windows: NonSendMut<LayerShellWindows>,
 ) {
-    for (entity, window, layer_shell_settings) in &windows {
+    for (entity, window, layer_shell_settings, output) in &windows {
         let window_wrapper = wayland_surfaces.get_window_wrapper(entity);
         let surface = window_wrapper
             .expect("tried to assign role before creating surface!")
             .wl_surface();
 
-        let layer_shell =
-            LayerShell::bind(&globals, &queue_handle).expect("layer shell not available!");
         let layer = layer_shell.create_layer_surface(
             &queue_handle,
             surface.clone(),
             layer_shell_settings.layer,
             Some("simple_layer"),
-            None,
+            output.map(|output| &output.0),
         );
 
         let _ = layer_shell_windows.insert(
@@ -164,15 +347,196 @@ fn assign_layer_shell_role(
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn update_layer_shell_settings(
     mut layer_shell_windows: NonSendMut<LayerShellWindows>,
-    windows: Query<(Entity, &Window, &LayerShellSettings), Without<SurfaceConfigured>>,
+    windows: Query<
+        (
+            Entity,
+            &Window,
+            &LayerShellSettings,
+            Has<LayerShellAutoMargin>,
+            Option<&LayerShellOutput>,
+        ),
+        With<SurfaceConfigured>,
+    >,
+) {
+    let exclusive_zones: Vec<ExclusiveZone> = windows
+        .iter()
+        .filter_map(|(entity, _, layer_shell_settings, _, output)| {
+            Some(ExclusiveZone {
+                entity,
+                edge: layer_shell_settings.exclusive_edge()?,
+                exclusive_zone: layer_shell_settings.exclusive_zone,
+                layer: layer_shell_settings.layer,
+                output: output.map(|output| output.0.id()),
+            })
+        })
+        .collect();
+
+    for (entity, window, layer_shell_settings, auto_margin, output) in &windows {
+        let Some(layer_shell_window) = layer_shell_windows.get_mut(&entity) else {
+            continue;
+        };
+        layer_shell_window.set_window_size((window.width() as u32, window.height() as u32));
+
+        let mut layer_shell_settings = layer_shell_settings.clone();
+        if auto_margin {
+            let output = output.map(|output| output.0.id());
+            layer_shell_settings.margin = offset_margin(
+                entity,
+                &layer_shell_settings,
+                output.as_ref(),
+                &exclusive_zones,
+            );
+        }
+        layer_shell_window.set_settings(layer_shell_settings);
+    }
+}
+
+/// Exclusive zone of a layer shell window, see [`LayerShellAutoMargin`].
+struct ExclusiveZone {
+    entity: Entity,
+    edge: Anchor,
+    exclusive_zone: i32,
+    layer: Layer,
+    /// `None` if the compositor picks the output.
+    output: Option<ObjectId>,
+}
+
+/// Returns the margin of `layer_shell_settings` offset by the exclusive zones of the other windows
+/// on the same edges and output, on the same layer or a higher one.
+fn offset_margin(
+    entity: Entity,
+    layer_shell_settings: &LayerShellSettings,
+    output: Option<&ObjectId>,
+    exclusive_zones: &[ExclusiveZone],
+) -> (i32, i32, i32, i32) {
+    let margin = layer_shell_settings.margin;
+    if layer_shell_settings.exclusive_zone >= 0 {
+        return margin;
+    }
+    let offset = |edge: Anchor| -> i32 {
+        if !layer_shell_settings.anchor.contains(edge) {
+            return 0;
+        }
+        exclusive_zones
+            .iter()
+            .filter(|exclusive_zone| {
+                exclusive_zone.entity != entity
+                    && exclusive_zone.edge == edge
+                    && exclusive_zone.layer >= layer_shell_settings.layer
+                    && exclusive_zone.output.as_ref() == output
+            })
+            .map(|exclusive_zone| exclusive_zone.exclusive_zone)
+            .sum()
+    };
+    let (top, right, bottom, left) = margin;
+    (
+        top + offset(Anchor::TOP),
+        right + offset(Anchor::RIGHT),
+        bottom + offset(Anchor::BOTTOM),
+        left + offset(Anchor::LEFT),
+    )
+}
+
+type KeyboardInteractivityChangedFilter = Or<(
+    Changed<LayerShellKeyboardInteractivity>,
+    Added<SurfaceConfigured>,
+)>;
+
+fn update_keyboard_interactivity(
+    mut layer_shell_windows: NonSendMut<LayerShellWindows>,
+    changed_windows: Query<
+        (Entity, &LayerShellKeyboardInteractivity),
+        KeyboardInteractivityChangedFilter,
+    >,
+    mut removed: RemovedComponents<LayerShellKeyboardInteractivity>,
+) {
+    for entity in removed.read() {
+        if let Some(layer_shell_window) = layer_shell_windows.get_mut(&entity) {
+            layer_shell_window.set_keyboard_interactivity(None);
+        }
+    }
+    for (entity, keyboard_interactivity) in &changed_windows {
+        if let Some(layer_shell_window) = layer_shell_windows.get_mut(&entity) {
+            layer_shell_window.set_keyboard_interactivity(Some(**keyboard_interactivity));
+        }
+    }
+}
+
+fn release_layer_surfaces(
+    mut closed: RemovedComponents<Window>,
+    mut layer_shell_windows: NonSendMut<LayerShellWindows>,
+    mut layer_surfaces_to_drop: Local<DeferredDrop<LayerShellWindow>>,
+) {
+    layer_surfaces_to_drop.drop_previous();
+    for entity in closed.read() {
+        if let Some(layer_shell_window) = layer_shell_windows.remove(&entity) {
+            layer_surfaces_to_drop.defer(layer_shell_window);
+        }
+    }
+}
+
+fn handle_focus_requests(
+    mut focus_requests: EventReader<RequestFocus>,
+    mut layer_shell_windows: NonSendMut<LayerShellWindows>,
+    active_window: Option<Res<ActiveWindow>>,
 ) {
-    for (entity, window, layer_shell_settings) in &windows {
-        let layer_shell_window = layer_shell_windows.get_mut(&entity).unwrap();
-        let window_size = (window.width() as u32, window.height() as u32);
-        layer_shell_window.window_size = window_size;
-        layer_shell_window.set_settings(layer_shell_settings.clone());
+    for focus_request in focus_requests.read() {
+        if active_window
+            .as_ref()
+            .is_some_and(|active_window| ***active_window == focus_request.window)
+        {
+            continue;
+        }
+        let Some(layer_shell_window) = layer_shell_windows.get_mut(&focus_request.window) else {
+            warn!("Focus was requested for a window without a layer surface");
+            continue;
+        };
+        layer_shell_window.focus_requested = true;
+        layer_shell_window
+            .layer_surface
+            .set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+        layer_shell_window.layer_surface.commit();
+    }
+}
+
+fn release_focus_requests(
+    mut window_focused_events: EventReader<WindowFocused>,
+    mut layer_shell_windows: NonSendMut<LayerShellWindows>,
+) {
+    for window_focused in window_focused_events.read() {
+        let Some(layer_shell_window) = layer_shell_windows.get_mut(&window_focused.window) else {
+            continue;
+        };
+        if !layer_shell_window.focus_requested {
+            continue;
+        }
+        // `OnDemand` keeps the focus without preventing the user from focusing other surfaces,
+        // windows configured as `Exclusive` stay exclusive.
+        let keyboard_interactivity = if window_focused.focused {
+            match layer_shell_window.keyboard_interactivity() {
+                KeyboardInteractivity::Exclusive => KeyboardInteractivity::Exclusive,
+                _ => KeyboardInteractivity::OnDemand,
+            }
+        } else {
+            layer_shell_window.focus_requested = false;
+            layer_shell_window.keyboard_interactivity()
+        };
+        layer_shell_window
+            .layer_surface
+            .set_keyboard_interactivity(keyboard_interactivity);
+        layer_shell_window.layer_surface.commit();
+    }
+}
+
+impl WaylandState {
+    fn layer_window_entity(&self, layer: &LayerSurface) -> Option<Entity> {
+        self.world()
+            .non_send_resource::<WaylandSurfaces>()
+            .get_window_entity(&layer.wl_surface().id())
+            .copied()
     }
 }
 
@@ -181,18 +545,194 @@ impl LayerShellHandler for WaylandState {
         &mut self,
         _conn: &smithay_client_toolkit::reexports::client::Connection,
         _qh: &QueueHandle<Self>,
-        _layer: &smithay_client_toolkit::shell::wlr_layer::LayerSurface,
+        layer: &smithay_client_toolkit::shell::wlr_layer::LayerSurface,
     ) {
+        let Some(window) = self.layer_window_entity(layer) else {
+            return;
+        };
+        info!("Layer surface was closed by the compositor");
+        let close_requested = WindowCloseRequested { window };
+        self.world_mut().send_event(close_requested.clone());
+        self.world_mut()
+            .send_event(WindowEvent::WindowCloseRequested(close_requested));
     }
 
     fn configure(
         &mut self,
         _conn: &smithay_client_toolkit::reexports::client::Connection,
         _qh: &QueueHandle<Self>,
-        _layer: &smithay_client_toolkit::shell::wlr_layer::LayerSurface,
-        _configure: smithay_client_toolkit::shell::wlr_layer::LayerSurfaceConfigure,
+        layer: &smithay_client_toolkit::shell::wlr_layer::LayerSurface,
+        configure: smithay_client_toolkit::shell::wlr_layer::LayerSurfaceConfigure,
         _serial: u32,
     ) {
+        let Some(window) = self.layer_window_entity(layer) else {
+            return;
+        };
+        let (width, height) = configure.new_size;
+        self.world_mut().send_event(LayerShellConfigure {
+            window,
+            width,
+            height,
+        });
+        self.remap_hidden_window(window);
+
+        let Some(mut bevy_window) = self.world_mut().get_mut::<Window>(window) else {
+            return;
+        };
+        let width = if width == 0 {
+            bevy_window.width()
+        } else {
+            width as f32
+        };
+        let height = if height == 0 {
+            bevy_window.height()
+        } else {
+            height as f32
+        };
+        if bevy_window.width() == width && bevy_window.height() == height {
+            return;
+        }
+        bevy_window.resolution.set(width, height);
+        let window_resized = WindowResized {
+            window,
+            width,
+            height,
+        };
+        self.world_mut().send_event(window_resized.clone());
+        self.world_mut()
+            .send_event(WindowEvent::WindowResized(window_resized));
     }
 }
 delegate_layer!(WaylandState);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(edge: Anchor, exclusive_zone: i32) -> LayerShellSettings {
+        let perpendicular = if edge.intersects(Anchor::TOP | Anchor::BOTTOM) {
+            Anchor::LEFT | Anchor::RIGHT
+        } else {
+            Anchor::TOP | Anchor::BOTTOM
+        };
+        LayerShellSettings {
+            anchor: edge | perpendicular,
+            exclusive_zone,
+            ..default()
+        }
+    }
+
+    fn exclusive_zones(entity: Entity, settings: &LayerShellSettings) -> Vec<ExclusiveZone> {
+        settings
+            .exclusive_edge()
+            .map(|edge| ExclusiveZone {
+                entity,
+                edge,
+                exclusive_zone: settings.exclusive_zone,
+                layer: settings.layer,
+                output: None,
+            })
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn exclusive_edge_of_bars() {
+        assert_eq!(bar(Anchor::TOP, 30).exclusive_edge(), Some(Anchor::TOP));
+        assert_eq!(bar(Anchor::LEFT, 30).exclusive_edge(), Some(Anchor::LEFT));
+        let single_edge = LayerShellSettings {
+            anchor: Anchor::BOTTOM,
+            exclusive_zone: 30,
+            ..default()
+        };
+        assert_eq!(single_edge.exclusive_edge(), Some(Anchor::BOTTOM));
+    }
+
+    #[test]
+    fn no_exclusive_edge_without_zone_or_single_edge() {
+        assert_eq!(bar(Anchor::TOP, 0).exclusive_edge(), None);
+        assert_eq!(bar(Anchor::TOP, -1).exclusive_edge(), None);
+        let corner = LayerShellSettings {
+            anchor: Anchor::TOP | Anchor::LEFT,
+            exclusive_zone: 30,
+            ..default()
+        };
+        assert_eq!(corner.exclusive_edge(), None);
+        let fullscreen = LayerShellSettings {
+            exclusive_zone: 30,
+            ..LayerShellSettings::fullscreen_overlay()
+        };
+        assert_eq!(fullscreen.exclusive_edge(), None);
+    }
+
+    #[test]
+    fn offset_margin_by_other_exclusive_zones() {
+        let bar_entity = Entity::from_raw(1);
+        let window = Entity::from_raw(2);
+        let exclusive_zones = exclusive_zones(bar_entity, &bar(Anchor::TOP, 30));
+        let settings = LayerShellSettings {
+            anchor: Anchor::all(),
+            exclusive_zone: -1,
+            margin: (5, 5, 5, 5),
+            ..default()
+        };
+        assert_eq!(
+            offset_margin(window, &settings, None, &exclusive_zones),
+            (35, 5, 5, 5)
+        );
+        // A window doesn't offset itself.
+        assert_eq!(
+            offset_margin(bar_entity, &settings, None, &exclusive_zones),
+            (5, 5, 5, 5)
+        );
+    }
+
+    #[test]
+    fn offset_margin_only_without_exclusive_zone() {
+        let exclusive_zones = exclusive_zones(Entity::from_raw(1), &bar(Anchor::TOP, 30));
+        // The compositor already moves windows with an exclusive zone of 0 out of the way.
+        let settings = LayerShellSettings {
+            anchor: Anchor::all(),
+            exclusive_zone: 0,
+            ..default()
+        };
+        assert_eq!(
+            offset_margin(Entity::from_raw(2), &settings, None, &exclusive_zones),
+            (0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn offset_margin_by_higher_layers() {
+        // A drawer on the bottom layer below a status bar on the top layer.
+        let exclusive_zones = exclusive_zones(Entity::from_raw(1), &bar(Anchor::TOP, 30));
+        let settings = LayerShellSettings {
+            anchor: Anchor::all(),
+            exclusive_zone: -1,
+            layer: Layer::Bottom,
+            ..default()
+        };
+        assert_eq!(
+            offset_margin(Entity::from_raw(2), &settings, None, &exclusive_zones),
+            (30, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn offset_margin_ignores_lower_layers() {
+        let bottom_bar = LayerShellSettings {
+            layer: Layer::Bottom,
+            ..bar(Anchor::TOP, 30)
+        };
+        let exclusive_zones = exclusive_zones(Entity::from_raw(1), &bottom_bar);
+        let settings = LayerShellSettings {
+            anchor: Anchor::all(),
+            exclusive_zone: -1,
+            ..default()
+        };
+        assert_eq!(
+            offset_margin(Entity::from_raw(2), &settings, None, &exclusive_zones),
+            (0, 0, 0, 0)
+        );
+    }
+}
@@ -1,10 +1,15 @@
 use std::{
+    path::{Path, PathBuf},
     sync::mpsc::SendError,
     time::{Duration, Instant},
 };
 
-use bevy::{app::PluginsState, prelude::*};
+use bevy::{
+    app::{PluginGroupBuilder, PluginsState},
+    prelude::*,
+};
 use smithay_client_toolkit::{
+    compositor::CompositorState,
     delegate_registry,
     output::OutputState,
     reexports::{
@@ -17,19 +22,53 @@ use smithay_client_toolkit::{
     seat::SeatState,
 };
 
+mod capabilities;
+pub mod connection;
+pub mod error;
 pub mod foreign_toplevel_manager;
+pub mod gamma_control;
 mod input_handler;
+pub mod input_latency;
+#[cfg(feature = "input_recording")]
+pub mod input_recording;
 pub mod input_region;
+pub mod kiosk_mode;
 pub mod layer_shell;
+pub mod opaque_region;
 mod output_handler;
+pub mod output_transform;
+pub mod panic_handler;
 pub mod session_lock;
 mod surface_handler;
+mod surface_region;
+pub mod surface_viewport;
+#[cfg(feature = "test_support")]
+pub mod synthetic_input;
+pub mod systemd_notify;
+pub mod ui_ticker;
+pub mod window_visibility;
 
 pub mod prelude {
+    pub use crate::capabilities::WaylandCapabilities;
+    pub use crate::connection::SecondaryConnection;
+    pub use crate::error::WaylandError;
+    pub use crate::gamma_control::{GammaRamp, OutputGammaEvent};
+    pub use crate::input_handler::{KeyboardLayoutSettings, KineticScrollSettings, LayoutChanged};
+    pub use crate::input_latency::{InputLatencyDiagnosticsPlugin, TimestampedInput};
     pub use crate::input_region::InputRegion;
-    pub use crate::layer_shell::{LayerShellSettings, LayerShellWindowSize};
-    pub use crate::session_lock::{SessionLockEvent, SessionLockWindow};
-    pub use crate::WaylandPlugin;
+    pub use crate::kiosk_mode::{KioskMode, KioskModePlugin, KioskWindow};
+    pub use crate::layer_shell::{
+        LayerShellAutoMargin, LayerShellConfigure, LayerShellKeyboardInteractivity,
+        LayerShellSettings, LayerShellWindowSize, RequestFocus,
+    };
+    pub use crate::opaque_region::OpaqueRegion;
+    pub use crate::output_transform::OutputTransformEvent;
+    pub use crate::panic_handler::PanicHandlerPlugin;
+    pub use crate::session_lock::{SessionLockEvent, SessionLockSecurity, SessionLockWindow};
+    pub use crate::surface_viewport::SurfaceViewport;
+    pub use crate::ui_ticker::{on_ui_tick, UiTicker};
+    pub use crate::window_visibility::{BackgroundWindow, RenderBudget};
+    pub use crate::{WaylandPlugin, WaylandPlugins};
     pub use smithay_client_toolkit::shell::wlr_layer::{Anchor, KeyboardInteractivity, Layer};
 }
 
@@ -45,52 +84,138 @@ impl ExternalEventDispatcher {
         self.0.send(Tick)
     }
 }
-#[derive(Default)]
-pub struct WaylandPlugin;
+/// Every plugin of this crate.
+///
+/// Protocols which aren't needed can be disabled, e.g.
+/// `WaylandPlugins.build().disable::<SessionLockPlugin>()`.
+pub struct WaylandPlugins;
+impl PluginGroup for WaylandPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(WaylandPlugin::default())
+            .add(layer_shell::LayerShellPlugin)
+            .add(session_lock::SessionLockPlugin)
+            .add(input_region::InputRegionPlugin)
+            .add(opaque_region::OpaqueRegionPlugin)
+            .add(surface_viewport::SurfaceViewportPlugin)
+            .add(foreign_toplevel_manager::ForeignToplevelManagerPlugin)
+            .add(output_transform::OutputTransformPlugin)
+            .add(gamma_control::GammaControlPlugin)
+            .add(systemd_notify::SystemdNotifyPlugin)
+            .add(window_visibility::WindowVisibilityPlugin)
+            .add(ui_ticker::UiTickerPlugin)
+    }
+}
+
+/// Connects to the compositor, handles surfaces, outputs and input and runs the app from the
+/// wayland event loop.
+pub struct WaylandPlugin {
+    /// Called instead of panicking when the connection can't be set up or a required global is
+    /// missing.
+    pub error_handler: error::WaylandErrorHandler,
+    /// Socket of the compositor, relative to `XDG_RUNTIME_DIR` unless absolute. Defaults to
+    /// `WAYLAND_DISPLAY`.
+    pub socket: Option<PathBuf>,
+    /// Socket of a [`connection::SecondaryConnection`] for privileged protocols, e.g. the one of the
+    /// parent compositor while developing a nested compositor.
+    pub secondary_socket: Option<PathBuf>,
+}
+impl WaylandPlugin {
+    /// Connects to `socket` instead of `WAYLAND_DISPLAY`.
+    pub fn with_socket(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: Some(socket.into()),
+            ..Default::default()
+        }
+    }
+}
+impl Default for WaylandPlugin {
+    fn default() -> Self {
+        Self {
+            error_handler: error::panic_on_error,
+            socket: None,
+            secondary_socket: None,
+        }
+    }
+}
 impl Plugin for WaylandPlugin {
     fn build(&self, app: &mut App) {
-        let connection =
-            Connection::connect_to_env().expect("Failed to connect to wayland socket!");
-        let event_loop =
-            EventLoop::<WaylandState>::try_new().expect("Failed to create event_loop!");
-        let (globals, event_queue) = registry_queue_init::<WaylandState>(&connection)
-            .expect("Failed to init registry queue");
-
-        let qh = event_queue.handle();
-        let loop_handle = event_loop.handle();
-        WaylandSource::new(connection.clone(), event_queue)
+        app.insert_resource(error::ErrorHandler(self.error_handler));
+        if let Err(error) = connect(
+            app,
+            self.socket.as_deref(),
+            self.secondary_socket.as_deref(),
+        ) {
+            error::report_error(app, error);
+        }
+    }
+}
+
+fn connect(
+    app: &mut App,
+    socket: Option<&Path>,
+    secondary_socket: Option<&Path>,
+) -> Result<(), error::WaylandError> {
+    let connection = match socket {
+        Some(socket) => connection::connect_to_socket(socket)?,
+        None => Connection::connect_to_env()?,
+    };
+    let event_loop = EventLoop::<WaylandState>::try_new()?;
+    let (globals, event_queue) = registry_queue_init::<WaylandState>(&connection)?;
+
+    let qh = event_queue.handle();
+    let compositor_state = CompositorState::bind(&globals, &qh).map_err(|error| {
+        error::WaylandError::MissingGlobal {
+            interface: "wl_compositor",
+            error,
+        }
+    })?;
+    let loop_handle = event_loop.handle();
+    WaylandSource::new(connection.clone(), event_queue)
+        .insert(loop_handle.clone())
+        .map_err(|error| error.error)?;
+
+    if let Some(secondary_socket) = secondary_socket {
+        let secondary_connection = connection::connect_to_socket(secondary_socket)?;
+        let secondary_queue = secondary_connection.new_event_queue();
+        let secondary_connection =
+            connection::SecondaryConnection::new(secondary_connection, secondary_queue.handle())?;
+        WaylandSource::new(secondary_connection.connection().clone(), secondary_queue)
             .insert(loop_handle.clone())
-            .expect("Failed to insert wayland source to event loop");
-
-        let (tx, rx) = calloop::channel::channel::<Tick>();
-        loop_handle
-            .insert_source(rx, |_, _, state| {
-                info!("External event was received!");
-                if state.plugins_state() == PluginsState::Cleaned {
-                    state.update();
-                }
-            })
-            .expect("Failed to insert external tick channel!");
-
-        app.insert_resource(ExternalEventDispatcher::new(tx));
-        app.insert_non_send_resource(RegistryState::new(&globals));
-        app.insert_non_send_resource(connection.clone());
-        app.insert_non_send_resource(globals);
-        app.insert_non_send_resource(qh);
-
-        app.add_plugins((
-            output_handler::OutputHandlerPlugin,
-            surface_handler::SurfaceHandlerPlugin,
-            input_handler::InputHandlerPlugin,
-            layer_shell::LayerShellPlugin,
-            session_lock::SessionLockPlugin,
-            input_region::InputRegionPlugin,
-            foreign_toplevel_manager::ForeignToplevelManagerPlugin,
-        ));
-        app.set_runner(|app| runner(app, event_loop));
+            .map_err(|error| error.error)?;
+        app.insert_non_send_resource(secondary_connection);
     }
+
+    let (tx, rx) = calloop::channel::channel::<Tick>();
+    loop_handle
+        .insert_source(rx, |_, _, state| {
+            trace!("External event was received");
+            if state.plugins_state() == PluginsState::Cleaned {
+                state.update();
+            }
+        })
+        .map_err(|error| error.error)?;
+
+    app.insert_resource(ExternalEventDispatcher::new(tx));
+    app.insert_resource(capabilities::WaylandCapabilities::new(&globals));
+    app.insert_non_send_resource(RegistryState::new(&globals));
+    app.insert_non_send_resource(compositor_state);
+    app.insert_non_send_resource(connection);
+    app.insert_non_send_resource(globals);
+    app.insert_non_send_resource(qh);
+
+    app.add_plugins((
+        output_handler::OutputHandlerPlugin,
+        surface_handler::SurfaceHandlerPlugin,
+        input_handler::InputHandlerPlugin,
+    ));
+    app.set_runner(|app| runner(app, event_loop));
+    Ok(())
 }
 
+/// Longest time the runner waits for events before updating the app anyway.
+const IDLE_TIMEOUT: Duration = Duration::from_millis(5000);
+
 pub fn runner(mut app: App, mut event_loop: EventLoop<'_, WaylandState>) -> AppExit {
     if app.plugins_state() == PluginsState::Ready {
         app.finish();
@@ -99,16 +224,47 @@ pub fn runner(mut app: App, mut event_loop: EventLoop<'_, WaylandState>) -> AppE
     let mut state = WaylandState(app);
     loop {
         let frame_start = Instant::now();
-        let _ = event_loop.dispatch(Duration::from_millis(5000), &mut state);
+        let timeout = idle_timeout(state.world());
+        dispatch(&mut event_loop, &mut state, timeout);
         if state.plugins_state() == PluginsState::Cleaned {
             state.update();
+            if let Some(app_exit) = state.should_exit() {
+                return app_exit;
+            }
         }
-        let _ = event_loop.dispatch(Duration::from_millis(0), &mut state);
+        dispatch(&mut event_loop, &mut state, Duration::ZERO);
         // TODO: Poll until delta time is greater than target frame time.
         if Instant::now() - frame_start < Duration::from_millis(16) {
+            let _span = debug_span!("frame_pacing").entered();
             std::thread::sleep(Duration::from_millis(16) - (frame_start - Instant::now()));
         }
-        let _ = event_loop.dispatch(Duration::from_millis(0), &mut state);
+        dispatch(&mut event_loop, &mut state, Duration::ZERO);
+    }
+}
+
+/// Returns how long the runner may wait for events, waking up in time for the next `on_ui_tick`
+/// and systemd watchdog ping even if the compositor sends no events.
+fn idle_timeout(world: &World) -> Duration {
+    let ui_tick = world
+        .get_resource::<ui_ticker::UiTicker>()
+        .and_then(|ui_ticker| ui_ticker.time_until_next_tick());
+    let watchdog_ping = world
+        .get_resource::<systemd_notify::SystemdNotify>()
+        .and_then(|systemd_notify| systemd_notify.time_until_watchdog_ping());
+    [ui_tick, watchdog_ping]
+        .into_iter()
+        .flatten()
+        .fold(IDLE_TIMEOUT, Duration::min)
+}
+
+fn dispatch(
+    event_loop: &mut EventLoop<'_, WaylandState>,
+    state: &mut WaylandState,
+    timeout: Duration,
+) {
+    let _span = debug_span!("wayland_dispatch", timeout_ms = timeout.as_millis()).entered();
+    if let Err(error) = event_loop.dispatch(timeout, state) {
+        error!("Dispatching the event loop failed! {:?}", error);
     }
 }
 
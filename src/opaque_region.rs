@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use smithay_client_toolkit::reexports::client::protocol::{
+    wl_region::WlRegion, wl_surface::WlSurface,
+};
+
+use crate::{
+    error::is_connected,
+    surface_region::{update_surface_regions, SurfaceRegion},
+};
+
+/// Marks the part of a window, in logical pixels, whose content is fully opaque.
+///
+/// The compositor can skip blending and drawing whatever is below it, e.g. for a status bar or a
+/// background. Transparent pixels inside the region may be drawn incorrectly.
+#[derive(Component, Deref)]
+pub struct OpaqueRegion(pub Rect);
+impl SurfaceRegion for OpaqueRegion {
+    fn set_region(surface: &WlSurface, region: Option<&WlRegion>) {
+        surface.set_opaque_region(region);
+    }
+}
+
+pub struct OpaqueRegionPlugin;
+impl Plugin for OpaqueRegionPlugin {
+    fn build(&self, app: &mut App) {
+        if !is_connected(app) {
+            return;
+        }
+        app.add_systems(Update, update_surface_regions::<OpaqueRegion>);
+    }
+}
@@ -0,0 +1,242 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+use smithay_client_toolkit::{
+    reexports::client::{
+        backend::ObjectId, event_created_child, protocol::wl_output::Transform, Dispatch, Proxy,
+        QueueHandle, WEnum,
+    },
+    registry::RegistryState,
+};
+use wayland_protocols_wlr::output_management::v1::client::{
+    zwlr_output_configuration_head_v1::ZwlrOutputConfigurationHeadV1,
+    zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+    zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+    zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+    zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+};
+
+use crate::{
+    capabilities::WaylandCapabilities, connection::SecondaryConnection, error::is_connected,
+    WaylandState,
+};
+
+/// Requests a new transform for one or all outputs through wlr-output-management.
+///
+/// `transform` is a [`wl_output::Transform`](smithay_client_toolkit::reexports::client::protocol::wl_output::Transform).
+#[derive(Debug, Clone, Event)]
+pub enum OutputTransformEvent {
+    /// Sets the transform of the output with the given name, or of every enabled output if
+    /// `output_name` is `None`.
+    Set {
+        output_name: Option<String>,
+        transform: Transform,
+    },
+}
+
+struct OutputHead {
+    head: ZwlrOutputHeadV1,
+    name: Option<String>,
+    enabled: bool,
+    transform: Transform,
+}
+
+#[derive(Default)]
+struct OutputHeads {
+    heads: HashMap<ObjectId, OutputHead>,
+    /// Serial of the last `done` event, required to create a configuration.
+    serial: Option<u32>,
+}
+
+pub struct OutputTransformPlugin;
+impl Plugin for OutputTransformPlugin {
+    fn build(&self, app: &mut App) {
+        if !is_connected(app) {
+            return;
+        }
+        let registry_state = app.world().non_send_resource::<RegistryState>();
+        let queue_handle = app.world().non_send_resource::<QueueHandle<WaylandState>>();
+        let output_manager = match app.world().get_non_send_resource::<SecondaryConnection>() {
+            Some(secondary_connection) => {
+                secondary_connection.bind::<ZwlrOutputManagerV1, _>(1..=4, ())
+            }
+            None => registry_state.bind_one::<ZwlrOutputManagerV1, _, _>(queue_handle, 1..=4, ()),
+        };
+        if let Ok(output_manager) = output_manager {
+            info!("Output manager was bound!");
+            app.world_mut()
+                .resource_mut::<WaylandCapabilities>()
+                .output_management = true;
+            app.insert_non_send_resource(output_manager);
+            app.insert_non_send_resource(OutputHeads::default());
+            app.add_event::<OutputTransformEvent>();
+            app.add_systems(Update, output_transform_event_handler);
+        } else {
+            let bind_error = output_manager.err().unwrap();
+            error!("Couldn't bind output manager! {:?}", bind_error);
+        }
+    }
+}
+
+fn output_transform_event_handler(
+    output_manager: NonSend<ZwlrOutputManagerV1>,
+    output_heads: NonSend<OutputHeads>,
+    queue_handle: NonSend<QueueHandle<WaylandState>>,
+    secondary_connection: Option<NonSend<SecondaryConnection>>,
+    mut events: EventReader<OutputTransformEvent>,
+) {
+    // Objects have to be created on the connection the manager was bound on.
+    let queue_handle = secondary_connection
+        .as_ref()
+        .map_or(&*queue_handle, |secondary_connection| {
+            secondary_connection.queue_handle()
+        });
+    for event in events.read() {
+        let OutputTransformEvent::Set {
+            output_name,
+            transform,
+        } = event;
+
+        let Some(serial) = output_heads.serial else {
+            warn!("Output transform requested before the output configuration was received");
+            continue;
+        };
+
+        // Every head has to be part of the configuration, so unaffected heads are re-enabled
+        // (or disabled) as they are.
+        let configuration = output_manager.create_configuration(serial, queue_handle, ());
+        for output_head in output_heads.heads.values() {
+            if !output_head.enabled {
+                configuration.disable_head(&output_head.head);
+                continue;
+            }
+            let configuration_head = configuration.enable_head(&output_head.head, queue_handle, ());
+            let is_target = output_name
+                .as_ref()
+                .is_none_or(|output_name| output_head.name.as_ref() == Some(output_name));
+            if is_target && output_head.transform != *transform {
+                configuration_head.set_transform(*transform);
+            }
+        }
+        configuration.apply();
+    }
+}
+
+impl Dispatch<ZwlrOutputManagerV1, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrOutputManagerV1,
+        event: zwlr_output_manager_v1::Event,
+        _data: &(),
+        _conn: &smithay_client_toolkit::reexports::client::Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let mut output_heads = state.world_mut().non_send_resource_mut::<OutputHeads>();
+        match event {
+            zwlr_output_manager_v1::Event::Head { head } => {
+                output_heads.heads.insert(
+                    head.id(),
+                    OutputHead {
+                        head,
+                        name: None,
+                        enabled: false,
+                        transform: Transform::Normal,
+                    },
+                );
+            }
+            zwlr_output_manager_v1::Event::Done { serial } => {
+                output_heads.serial = Some(serial);
+            }
+            zwlr_output_manager_v1::Event::Finished => {
+                output_heads.heads.clear();
+                output_heads.serial = None;
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(WaylandState, ZwlrOutputManagerV1, [
+        zwlr_output_manager_v1::EVT_HEAD_OPCODE => (ZwlrOutputHeadV1, ())
+    ]);
+}
+
+impl Dispatch<ZwlrOutputHeadV1, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrOutputHeadV1,
+        event: zwlr_output_head_v1::Event,
+        _data: &(),
+        _conn: &smithay_client_toolkit::reexports::client::Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let mut output_heads = state.world_mut().non_send_resource_mut::<OutputHeads>();
+        if let zwlr_output_head_v1::Event::Finished = event {
+            if proxy.version() >= 3 {
+                proxy.release();
+            }
+            output_heads.heads.remove(&proxy.id());
+            return;
+        }
+        let Some(output_head) = output_heads.heads.get_mut(&proxy.id()) else {
+            return;
+        };
+        match event {
+            zwlr_output_head_v1::Event::Name { name } => output_head.name = Some(name),
+            zwlr_output_head_v1::Event::Enabled { enabled } => output_head.enabled = enabled != 0,
+            zwlr_output_head_v1::Event::Transform {
+                transform: WEnum::Value(transform),
+            } => output_head.transform = transform,
+            _ => {}
+        }
+    }
+
+    event_created_child!(WaylandState, ZwlrOutputHeadV1, [
+        zwlr_output_head_v1::EVT_MODE_OPCODE => (ZwlrOutputModeV1, ())
+    ]);
+}
+
+impl Dispatch<ZwlrOutputModeV1, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        proxy: &ZwlrOutputModeV1,
+        event: zwlr_output_mode_v1::Event,
+        _data: &(),
+        _conn: &smithay_client_toolkit::reexports::client::Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if matches!(event, zwlr_output_mode_v1::Event::Finished) && proxy.version() >= 3 {
+            proxy.release();
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationV1, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        proxy: &ZwlrOutputConfigurationV1,
+        event: zwlr_output_configuration_v1::Event,
+        _data: &(),
+        _conn: &smithay_client_toolkit::reexports::client::Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_output_configuration_v1::Event::Succeeded => info!("Output transform applied"),
+            zwlr_output_configuration_v1::Event::Failed => error!("Output transform failed"),
+            zwlr_output_configuration_v1::Event::Cancelled => {
+                warn!("Output transform was cancelled by the compositor")
+            }
+            _ => {}
+        }
+        proxy.destroy();
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationHeadV1, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwlrOutputConfigurationHeadV1,
+        _event: <ZwlrOutputConfigurationHeadV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &smithay_client_toolkit::reexports::client::Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
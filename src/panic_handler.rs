@@ -0,0 +1,219 @@
+use std::{
+    os::unix::process::CommandExt,
+    process::Command,
+    sync::{Mutex, TryLockError},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use smithay_client_toolkit::{
+    reexports::client::Connection, session_lock::SessionLock, shell::wlr_layer::SurfaceKind,
+};
+use wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::ZwlrLayerSurfaceV1;
+
+use crate::{layer_shell::LayerShellWindows, session_lock::SessionLockWrapper};
+
+/// Everything needed to restore the session from the panic hook or the watchdog thread, which
+/// can't access the world.
+static SESSION_RESTORE: Mutex<Option<SessionRestore>> = Mutex::new(None);
+
+/// Restores the session when the app panics or stops responding.
+///
+/// Normally the compositor cleans up after a client disconnects, but that doesn't happen while the
+/// process hangs and, by design, a session lock stays locked when its client dies. This plugin
+/// installs a panic hook and a watchdog thread which destroy the layer surfaces, optionally unlock
+/// the session and optionally restart the process.
+///
+/// Panics on any thread restore the session. Systems run on the task pool threads and the executor
+/// re-raises their panics on the app thread without calling the panic hook again, so the hook
+/// can't tell which panics take the app down.
+///
+/// Has to be added after [`WaylandPlugin`](crate::WaylandPlugin).
+#[derive(Debug, Clone)]
+pub struct PanicHandlerPlugin {
+    /// Unlocks an active session lock on panic.
+    ///
+    /// This means crashing a lock screen unlocks the session, so it is disabled by default.
+    pub unlock_session: bool,
+    /// Re-executes the process with the same arguments after restoring the session.
+    pub restart: bool,
+    /// Time without a frame after which the app is considered stuck, restored like on a panic and
+    /// exited. `None` disables the watchdog.
+    ///
+    /// The runner updates the app at least every 5 seconds, so this has to be longer than that.
+    pub watchdog_timeout: Option<Duration>,
+}
+impl Default for PanicHandlerPlugin {
+    fn default() -> Self {
+        Self {
+            unlock_session: false,
+            restart: false,
+            watchdog_timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+impl Plugin for PanicHandlerPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(connection) = app.world().get_non_send_resource::<Connection>() else {
+            warn!("PanicHandlerPlugin has to be added after a connected WaylandPlugin");
+            return;
+        };
+        let connection = connection.clone();
+        *lock_session_restore().expect("session restore is already in use!") =
+            Some(SessionRestore {
+                connection,
+                unlock_session: self.unlock_session,
+                restart: self.restart,
+                layer_surfaces: Vec::new(),
+                session_lock: None,
+                heartbeat: None,
+            });
+
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |panic_info| {
+            previous_hook(panic_info);
+            restore_session();
+        }));
+
+        if let Some(watchdog_timeout) = self.watchdog_timeout {
+            std::thread::spawn(move || watchdog(watchdog_timeout));
+        }
+
+        app.add_systems(Last, update_session_restore);
+    }
+}
+
+struct SessionRestore {
+    connection: Connection,
+    unlock_session: bool,
+    restart: bool,
+    layer_surfaces: Vec<ZwlrLayerSurfaceV1>,
+    session_lock: Option<SessionLock>,
+    /// Time of the last frame, `None` until the first frame finished.
+    heartbeat: Option<Instant>,
+}
+impl SessionRestore {
+    fn run(self) {
+        let session_lock = self.session_lock.filter(|_| self.unlock_session);
+        if let Some(session_lock) = session_lock {
+            session_lock.unlock();
+        }
+        for layer_surface in &self.layer_surfaces {
+            layer_surface.destroy();
+        }
+        if let Err(error) = self.connection.flush() {
+            error!("Couldn't flush the connection while restoring the session! {error}");
+        }
+        info!("Session was restored");
+
+        if self.restart {
+            restart();
+        }
+    }
+}
+
+/// Locks the session restore without blocking, since the panic may have happened while it was
+/// locked.
+fn lock_session_restore() -> Option<std::sync::MutexGuard<'static, Option<SessionRestore>>> {
+    match SESSION_RESTORE.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Restores the session once, later calls do nothing.
+fn restore_session() {
+    let Some(session_restore) = lock_session_restore().and_then(|mut guard| guard.take()) else {
+        return;
+    };
+    session_restore.run();
+}
+
+fn restart() {
+    let Ok(executable) = std::env::current_exe() else {
+        error!("Couldn't find the executable to restart!");
+        return;
+    };
+    let error = Command::new(executable)
+        .args(std::env::args_os().skip(1))
+        .exec();
+    error!("Couldn't restart the app! {error}");
+}
+
+fn watchdog(timeout: Duration) {
+    loop {
+        std::thread::sleep(timeout / 4);
+        let stuck = match SESSION_RESTORE.lock() {
+            Ok(session_restore) => match session_restore.as_ref() {
+                Some(session_restore) => session_restore
+                    .heartbeat
+                    .is_some_and(|heartbeat| heartbeat.elapsed() > timeout),
+                // The session was already restored after a panic.
+                None => return,
+            },
+            Err(_) => return,
+        };
+        if stuck {
+            error!("App didn't update for {timeout:?}, restoring the session");
+            restore_session();
+            std::process::exit(1);
+        }
+    }
+}
+
+fn update_session_restore(
+    layer_shell_windows: Option<NonSend<LayerShellWindows>>,
+    session_lock_wrapper: Option<NonSend<SessionLockWrapper>>,
+) {
+    let Ok(mut session_restore) = SESSION_RESTORE.lock() else {
+        return;
+    };
+    let Some(session_restore) = session_restore.as_mut() else {
+        return;
+    };
+    session_restore.heartbeat = Some(Instant::now());
+    if let Some(layer_shell_windows) = layer_shell_windows.filter(|windows| windows.is_changed()) {
+        session_restore.layer_surfaces = layer_shell_windows
+            .layer_surfaces()
+            .filter_map(|layer_surface| match layer_surface.kind() {
+                SurfaceKind::Wlr(wlr_layer_surface) => Some(wlr_layer_surface.clone()),
+                _ => None,
+            })
+            .collect();
+    }
+    if let Some(session_lock_wrapper) = session_lock_wrapper.filter(|wrapper| wrapper.is_changed())
+    {
+        session_restore.session_lock = (**session_lock_wrapper).clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::net::UnixStream, panic::AssertUnwindSafe};
+
+    use bevy::ecs::schedule::ExecutorKind;
+
+    use super::*;
+
+    #[test]
+    fn system_panic_restores_session() {
+        let (client, _server) = UnixStream::pair().unwrap();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_non_send_resource(Connection::from_socket(client).unwrap())
+            .add_plugins(PanicHandlerPlugin {
+                watchdog_timeout: None,
+                ..default()
+            })
+            .edit_schedule(Update, |schedule| {
+                schedule.set_executor_kind(ExecutorKind::MultiThreaded);
+            })
+            .add_systems(Update, || panic!("system panicked"));
+        assert!(SESSION_RESTORE.lock().unwrap().is_some());
+
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| app.update()));
+        assert!(result.is_err());
+        assert!(SESSION_RESTORE.lock().unwrap().is_none());
+    }
+}
@@ -7,7 +7,12 @@ use smithay_client_toolkit::{
 };
 
 use crate::{
-    surface_handler::{create_windows, SurfaceConfigured, WaylandSurfaces},
+    capabilities::WaylandCapabilities,
+    error::is_connected,
+    input_handler::ActiveWindow,
+    kiosk_mode::KioskMode,
+    layer_shell::{LayerShellOutput, LayerShellSettings, RequestFocus},
+    surface_handler::{create_windows, DeferredDrop, SurfaceConfigured, WaylandSurfaces},
     WaylandState,
 };
 
@@ -30,67 +35,194 @@ impl SessionLockUnconfiguredWindow {
     }
 }
 
+/// Matches lock windows whether or not their lock surface was created yet.
+type LockWindowFilter = Or<(With<SessionLockWindow>, With<SessionLockUnconfiguredWindow>)>;
+
 #[derive(Clone, Copy, Event)]
 pub enum SessionLockEvent {
     Lock,
     Unlock,
+    /// Sent once the session was unlocked, every lock window was despawned and keyboard focus was
+    /// handed back to the window which had it before locking.
+    Unlocked,
+}
+
+/// How the session is locked, so a lock screen can tell users how secure the lock is.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionLockSecurity {
+    /// The compositor supports `ext_session_lock_v1` and guarantees that only the lock windows are
+    /// shown and receive input, even if the app crashes.
+    SessionLock,
+    /// The lock is emulated with fullscreen overlay layer surfaces grabbing the keyboard. Other
+    /// overlay surfaces can still be shown above them and the session is unlocked if the app exits.
+    LayerShellFallback,
+    /// Neither `ext_session_lock_v1` nor the layer shell is available, locking does nothing.
+    Unavailable,
+}
+
+/// State needed to restore the session once the lock is released.
+#[derive(Resource, Default)]
+struct SessionLockRestore {
+    focused_window: Option<Entity>,
+    unlocking: bool,
+    /// Set while the session is locked with [`SessionLockSecurity::LayerShellFallback`].
+    fallback_locked: bool,
 }
 
 pub struct SessionLockPlugin;
 impl Plugin for SessionLockPlugin {
     fn build(&self, app: &mut App) {
+        if !is_connected(app) {
+            return;
+        }
         let globals = app.world().non_send_resource::<GlobalList>();
         let queue_handle = app.world().non_send_resource::<QueueHandle<WaylandState>>();
         let session_lock_state = SessionLockState::new(globals, queue_handle);
+        let mut capabilities = app.world_mut().resource_mut::<WaylandCapabilities>();
+        // SessionLockState binds version 1, so the bind succeeded if the global is advertised.
+        capabilities.session_lock = capabilities
+            .version("ext_session_lock_manager_v1")
+            .is_some();
+        // Set by LayerShellPlugin, which is built before this plugin.
+        let session_lock_security = if capabilities.session_lock {
+            SessionLockSecurity::SessionLock
+        } else if capabilities.layer_shell {
+            warn!("Session lock is not supported, falling back to layer shell surfaces");
+            SessionLockSecurity::LayerShellFallback
+        } else {
+            error!("Neither session lock nor layer shell is supported, locking does nothing");
+            SessionLockSecurity::Unavailable
+        };
 
         app.insert_non_send_resource(session_lock_state);
         app.insert_non_send_resource(SessionLockWindows::default());
         app.insert_non_send_resource(SessionLockWrapper::default());
+        app.init_resource::<SessionLockRestore>();
+        app.insert_resource(session_lock_security);
         app.add_event::<SessionLockEvent>();
         app.add_systems(
             PreUpdate,
             (
                 session_lock_event_handler.before(create_windows),
+                restore_after_unlock.after(session_lock_event_handler),
                 configure_lock_surfaces.after(create_windows),
             ),
         );
+        app.add_systems(Last, release_lock_surfaces);
     }
 }
 
 #[derive(Deref, DerefMut, Default)]
-struct SessionLockWrapper(Option<SessionLock>);
+pub(crate) struct SessionLockWrapper(Option<SessionLock>);
+#[allow(clippy::too_many_arguments)]
 fn session_lock_event_handler(
     mut commands: Commands,
     mut session_lock_event_reader: EventReader<SessionLockEvent>,
     session_lock_state: NonSend<SessionLockState>,
     mut session_lock_wrapper: NonSendMut<SessionLockWrapper>,
+    mut session_lock_restore: ResMut<SessionLockRestore>,
+    session_lock_security: Res<SessionLockSecurity>,
+    kiosk_mode: Option<Res<KioskMode>>,
+    active_window: Option<Res<ActiveWindow>>,
     queue_handle: NonSend<QueueHandle<WaylandState>>,
     output_state: NonSend<OutputState>,
+    lock_windows: Query<Entity, LockWindowFilter>,
 ) {
     for session_lock_event in session_lock_event_reader.read() {
         match session_lock_event {
             SessionLockEvent::Lock => {
-                if session_lock_wrapper.is_some() {
+                if kiosk_mode.is_some() {
+                    warn!("Session lock is disabled in kiosk mode");
+                    continue;
+                }
+                if session_lock_wrapper.is_some() || session_lock_restore.fallback_locked {
                     error!("Lock was called even if it was already aquired");
                     return;
                 }
-                let session_lock = session_lock_state
-                    .lock(&queue_handle)
-                    .expect("Unable to aquire session lock");
-                let _ = session_lock_wrapper.insert(session_lock);
-
-                for output in output_state.outputs() {
-                    commands.spawn((
-                        Window::default(),
-                        SessionLockUnconfiguredWindow::new(output),
-                    ));
+                match *session_lock_security {
+                    SessionLockSecurity::SessionLock => {
+                        let session_lock = session_lock_state
+                            .lock(&queue_handle)
+                            .expect("Unable to aquire session lock");
+                        let _ = session_lock_wrapper.insert(session_lock);
+                        for output in output_state.outputs() {
+                            commands.spawn((
+                                Window::default(),
+                                SessionLockUnconfiguredWindow::new(output),
+                            ));
+                        }
+                    }
+                    SessionLockSecurity::LayerShellFallback => {
+                        session_lock_restore.fallback_locked = true;
+                        for output in output_state.outputs() {
+                            commands.spawn((
+                                Window::default(),
+                                SessionLockWindow,
+                                LayerShellSettings::fullscreen_overlay(),
+                                LayerShellOutput(output),
+                            ));
+                        }
+                    }
+                    SessionLockSecurity::Unavailable => {
+                        error!("Session can't be locked on this compositor");
+                        continue;
+                    }
                 }
+                session_lock_restore.focused_window =
+                    active_window.as_ref().map(|active_window| ***active_window);
             }
             SessionLockEvent::Unlock => {
-                if let Some(session_lock) = &**session_lock_wrapper {
+                if let Some(session_lock) = session_lock_wrapper.take() {
                     session_lock.unlock();
+                } else if !session_lock_restore.fallback_locked {
+                    continue;
+                }
+                session_lock_restore.fallback_locked = false;
+
+                // The active window is left to the compositor, which sends `leave` for the lock
+                // surfaces.
+                for entity in &lock_windows {
+                    commands.entity(entity).despawn();
                 }
+                session_lock_restore.unlocking = true;
             }
+            SessionLockEvent::Unlocked => {}
+        }
+    }
+}
+
+fn restore_after_unlock(
+    mut session_lock_restore: ResMut<SessionLockRestore>,
+    focus_requests: Option<ResMut<Events<RequestFocus>>>,
+    lock_windows: Query<(), LockWindowFilter>,
+    windows: Query<(), With<Window>>,
+    mut session_lock_event_writer: EventWriter<SessionLockEvent>,
+) {
+    if !session_lock_restore.unlocking || !lock_windows.is_empty() {
+        return;
+    }
+    session_lock_restore.unlocking = false;
+
+    let focused_window = session_lock_restore
+        .focused_window
+        .take()
+        .filter(|entity| windows.contains(*entity));
+    // The compositor sends `enter` once it gave the window the focus, which sets the active window.
+    if let (Some(window), Some(mut focus_requests)) = (focused_window, focus_requests) {
+        focus_requests.send(RequestFocus { window });
+    }
+    session_lock_event_writer.write(SessionLockEvent::Unlocked);
+}
+
+fn release_lock_surfaces(
+    mut removed_lock_windows: RemovedComponents<SessionLockWindow>,
+    mut session_lock_windows: NonSendMut<SessionLockWindows>,
+    mut lock_surfaces_to_drop: Local<DeferredDrop<SessionLockWindowInternal>>,
+) {
+    lock_surfaces_to_drop.drop_previous();
+    for entity in removed_lock_windows.read() {
+        if let Some(session_lock_window) = session_lock_windows.remove(&entity) {
+            lock_surfaces_to_drop.defer(session_lock_window);
         }
     }
 }
@@ -3,7 +3,9 @@ use std::collections::HashMap;
 use bevy::{
     ecs::entity::EntityHashMap,
     prelude::*,
-    window::{RawHandleWrapper, RawHandleWrapperHolder, WindowCreated, WindowWrapper},
+    window::{
+        RawHandleWrapper, RawHandleWrapperHolder, WindowClosed, WindowCreated, WindowWrapper,
+    },
 };
 use raw_window_handle::{
     DisplayHandle, HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle,
@@ -24,13 +26,9 @@ pub struct SurfaceConfigured;
 pub struct SurfaceHandlerPlugin;
 impl Plugin for SurfaceHandlerPlugin {
     fn build(&self, app: &mut App) {
-        let queue_handle: &QueueHandle<WaylandState> = app.world().non_send_resource();
-        let globals = app.world().non_send_resource();
-        app.insert_non_send_resource(
-            CompositorState::bind(globals, queue_handle).expect("failed to bind compositor!"),
-        );
         app.insert_non_send_resource(WaylandSurfaces::default());
-        app.add_systems(PreUpdate, create_windows);
+        app.add_systems(PreUpdate, create_windows)
+            .add_systems(Last, despawn_windows);
     }
 }
 
@@ -110,6 +108,15 @@ impl WaylandSurfaces {
         self.windows.get(&surface_id).unwrap()
     }
 
+    /// Forgets the surface backing `entity`, returning its wrapper.
+    ///
+    /// The `wl_surface` itself is destroyed together with the role object that was assigned to it.
+    pub fn remove_surface(&mut self, entity: Entity) -> Option<WindowWrapper<WaylandSurface>> {
+        let surface_id = self.entity_to_surface.remove(&entity)?;
+        self.surface_to_entity.remove(&surface_id);
+        self.windows.remove(&surface_id)
+    }
+
     pub fn get_window_wrapper(&self, entity: Entity) -> Option<&WindowWrapper<WaylandSurface>> {
         self.entity_to_surface
             .get(&entity)
@@ -196,3 +203,47 @@ pub fn create_windows(
         window_created_event.write(WindowCreated { window: entity });
     }
 }
+
+/// Keeps values owning a `wl_surface` alive for one extra frame.
+///
+/// The render world presents to a surface until the frame after its window was closed, so the
+/// surface may only be destroyed once the render world has released its swapchain. Systems
+/// releasing surfaces call [`Self::drop_previous`] every frame before deferring the values
+/// released in this frame.
+pub(crate) struct DeferredDrop<T>(Vec<T>);
+impl<T> Default for DeferredDrop<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+impl<T> DeferredDrop<T> {
+    /// Drops the values deferred in the previous frame.
+    pub(crate) fn drop_previous(&mut self) {
+        self.0.clear();
+    }
+
+    /// Keeps `value` alive until the next call of [`Self::drop_previous`].
+    pub(crate) fn defer(&mut self, value: T) {
+        self.0.push(value);
+    }
+}
+
+pub fn despawn_windows(
+    mut closed: RemovedComponents<Window>,
+    window_entities: Query<Entity, With<Window>>,
+    mut wayland_surfaces: NonSendMut<WaylandSurfaces>,
+    mut windows_to_drop: Local<DeferredDrop<WindowWrapper<WaylandSurface>>>,
+    mut window_closed_event: EventWriter<WindowClosed>,
+) {
+    windows_to_drop.drop_previous();
+    for window in closed.read() {
+        // The component might have been removed and re-added within the same frame.
+        if window_entities.contains(window) {
+            continue;
+        }
+        if let Some(surface) = wayland_surfaces.remove_surface(window) {
+            windows_to_drop.defer(surface);
+        }
+        window_closed_event.write(WindowClosed { window });
+    }
+}
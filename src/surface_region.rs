@@ -0,0 +1,51 @@
+use std::ops::Deref;
+
+use bevy::prelude::*;
+use smithay_client_toolkit::{
+    compositor::{CompositorState, Region},
+    reexports::client::protocol::{wl_region::WlRegion, wl_surface::WlSurface},
+};
+
+use crate::surface_handler::{SurfaceConfigured, WaylandSurfaces};
+
+/// A component holding a region of a window's surface in logical pixels, like
+/// [`InputRegion`](crate::input_region::InputRegion).
+pub(crate) trait SurfaceRegion: Component + Deref<Target = Rect> {
+    /// Sets the region on `surface`, `None` restores the default.
+    fn set_region(surface: &WlSurface, region: Option<&WlRegion>);
+}
+
+type SurfaceRegionChangedFilter<R> = (
+    With<SurfaceConfigured>,
+    Or<(Changed<R>, Added<SurfaceConfigured>)>,
+);
+
+/// Sets the region `R` on the surfaces of windows whenever it changes.
+pub(crate) fn update_surface_regions<R: SurfaceRegion>(
+    compositor: NonSend<CompositorState>,
+    wayland_surfaces: NonSend<WaylandSurfaces>,
+    changed_windows: Query<(Entity, Option<&R>), SurfaceRegionChangedFilter<R>>,
+    mut removed: RemovedComponents<R>,
+) {
+    for entity in removed.read() {
+        if let Some(window_wrapper) = wayland_surfaces.get_window_wrapper(entity) {
+            R::set_region(window_wrapper.wl_surface(), None);
+        }
+    }
+    for (entity, surface_region) in &changed_windows {
+        let Some(window_wrapper) = wayland_surfaces.get_window_wrapper(entity) else {
+            continue;
+        };
+        let Some(surface_region) = surface_region else {
+            continue;
+        };
+        let region = Region::new(&*compositor).unwrap();
+        region.add(
+            surface_region.min.x as i32,
+            surface_region.min.y as i32,
+            surface_region.width() as i32,
+            surface_region.height() as i32,
+        );
+        R::set_region(window_wrapper.wl_surface(), Some(region.wl_region()));
+    }
+}
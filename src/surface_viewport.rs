@@ -0,0 +1,183 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+use smithay_client_toolkit::reexports::{
+    client::{globals::GlobalList, Connection, Dispatch, QueueHandle},
+    protocols::wp::viewporter::client::{
+        wp_viewport::{self, WpViewport},
+        wp_viewporter::{self, WpViewporter},
+    },
+};
+
+use crate::{
+    capabilities::WaylandCapabilities,
+    error::is_connected,
+    surface_handler::{SurfaceConfigured, WaylandSurfaces},
+    WaylandState,
+};
+
+/// Crops and scales the buffer of a window through `wp_viewporter`.
+///
+/// E.g. a blurred background can be rendered at half the resolution of the window and scaled up by
+/// the compositor by setting `destination` to the size of the window. Removing the component shows
+/// the buffer as is again.
+///
+/// A source with a negative position or an empty size and an empty destination are ignored with a
+/// warning, as if they were `None`.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct SurfaceViewport {
+    /// The part of the buffer to show, in buffer pixels divided by the buffer scale. The whole
+    /// buffer if `None`.
+    pub source: Option<Rect>,
+    /// The size of the surface in logical pixels, which the source is scaled to. The size of the
+    /// source if `None`, which then has to be integer.
+    pub destination: Option<UVec2>,
+}
+
+#[derive(Default, Deref, DerefMut)]
+struct SurfaceViewports(HashMap<Entity, WpViewport>);
+
+pub struct SurfaceViewportPlugin;
+impl Plugin for SurfaceViewportPlugin {
+    fn build(&self, app: &mut App) {
+        if !is_connected(app) {
+            return;
+        }
+        let globals = app.world().non_send_resource::<GlobalList>();
+        let queue_handle = app.world().non_send_resource::<QueueHandle<WaylandState>>();
+        let viewporter = match globals.bind::<WpViewporter, _, _>(queue_handle, 1..=1, ()) {
+            Ok(viewporter) => viewporter,
+            Err(error) => {
+                warn!(
+                    "Couldn't bind viewporter, SurfaceViewport does nothing! {:?}",
+                    error
+                );
+                return;
+            }
+        };
+
+        app.world_mut()
+            .resource_mut::<WaylandCapabilities>()
+            .viewporter = true;
+        app.insert_non_send_resource(viewporter)
+            .insert_non_send_resource(SurfaceViewports::default())
+            .add_systems(Update, update_surface_viewports);
+    }
+}
+
+type SurfaceViewportChangedFilter = Or<(Changed<SurfaceViewport>, Added<SurfaceConfigured>)>;
+
+fn update_surface_viewports(
+    viewporter: NonSend<WpViewporter>,
+    mut surface_viewports: NonSendMut<SurfaceViewports>,
+    wayland_surfaces: NonSend<WaylandSurfaces>,
+    queue_handle: NonSend<QueueHandle<WaylandState>>,
+    changed_windows: Query<
+        (Entity, &SurfaceViewport),
+        (With<SurfaceConfigured>, SurfaceViewportChangedFilter),
+    >,
+    mut removed: RemovedComponents<SurfaceViewport>,
+) {
+    // Destroying the viewport resets the source and destination with the next commit.
+    for entity in removed.read() {
+        if let Some(viewport) = surface_viewports.remove(&entity) {
+            viewport.destroy();
+        }
+    }
+    for (entity, surface_viewport) in &changed_windows {
+        let Some(window_wrapper) = wayland_surfaces.get_window_wrapper(entity) else {
+            continue;
+        };
+        let viewport = surface_viewports.entry(entity).or_insert_with(|| {
+            viewporter.get_viewport(window_wrapper.wl_surface(), &queue_handle, ())
+        });
+
+        // -1 unsets the source or destination. Invalid values are a protocol error which closes
+        // the connection, so they are unset as well.
+        let source = surface_viewport.source.filter(|&source| {
+            let valid = is_valid_source(source);
+            if !valid {
+                warn!("Ignoring invalid SurfaceViewport source {source:?}");
+            }
+            valid
+        });
+        match source {
+            Some(source) => viewport.set_source(
+                source.min.x as f64,
+                source.min.y as f64,
+                source.width() as f64,
+                source.height() as f64,
+            ),
+            None => viewport.set_source(-1.0, -1.0, -1.0, -1.0),
+        }
+        let destination = surface_viewport.destination.filter(|&destination| {
+            let valid = is_valid_destination(destination);
+            if !valid {
+                warn!("Ignoring invalid SurfaceViewport destination {destination}");
+            }
+            valid
+        });
+        match destination {
+            Some(destination) => {
+                viewport.set_destination(destination.x as i32, destination.y as i32)
+            }
+            None => viewport.set_destination(-1, -1),
+        }
+    }
+}
+
+/// `wp_viewport` requires a non-negative position and a positive size.
+fn is_valid_source(source: Rect) -> bool {
+    source.min.is_finite()
+        && source.max.is_finite()
+        && source.min.cmpge(Vec2::ZERO).all()
+        && source.width() > 0.0
+        && source.height() > 0.0
+}
+
+/// `wp_viewport` requires a positive size which fits into an `i32`.
+fn is_valid_destination(destination: UVec2) -> bool {
+    (1..=i32::MAX as u32).contains(&destination.x) && (1..=i32::MAX as u32).contains(&destination.y)
+}
+
+impl Dispatch<WpViewporter, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpViewporter,
+        _event: wp_viewporter::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WpViewport, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpViewport,
+        _event: wp_viewport::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_source() {
+        assert!(is_valid_source(Rect::new(0.0, 0.0, 10.0, 5.5)));
+        assert!(!is_valid_source(Rect::new(0.0, 0.0, 0.0, 5.0)));
+        assert!(!is_valid_source(Rect::new(-1.0, 0.0, 10.0, 5.0)));
+        assert!(!is_valid_source(Rect::new(0.0, 0.0, f32::INFINITY, 5.0)));
+    }
+
+    #[test]
+    fn valid_destination() {
+        assert!(is_valid_destination(UVec2::new(1, 1)));
+        assert!(!is_valid_destination(UVec2::new(0, 10)));
+        assert!(!is_valid_destination(UVec2::new(10, u32::MAX)));
+    }
+}
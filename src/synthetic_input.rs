@@ -0,0 +1,118 @@
+//! Injects input as if it came from the compositor, so apps can be tested without one.
+//!
+//! The events take the same path as the ones converted from wayland events, i.e. both the input
+//! event and the matching [`WindowEvent`] are sent and the cursor position of the window is
+//! updated. Positions are in logical pixels of the window.
+//!
+//! ```no_run
+//! # use bevy::{input::ButtonState, prelude::*};
+//! use bevy_wayland::synthetic_input::SyntheticInput;
+//!
+//! # fn press_button(app: &mut App, window: Entity) {
+//! app.world_mut().warp_pointer(window, Vec2::new(20.0, 20.0));
+//! app.update();
+//! app.world_mut()
+//!     .pointer_button(window, MouseButton::Left, ButtonState::Pressed);
+//! app.update();
+//! # }
+//! ```
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel},
+        touch::{TouchInput, TouchPhase},
+        ButtonState,
+    },
+    prelude::*,
+    window::{CursorEntered, CursorLeft, CursorMoved, WindowEvent},
+};
+
+use crate::input_handler::{send_window_event, set_cursor_position};
+
+/// Input injection for a [`World`] driven by [`App::update`] instead of the wayland runner.
+pub trait SyntheticInput {
+    /// Moves the pointer to `position`, entering `window` first if the pointer isn't over it.
+    fn warp_pointer(&mut self, window: Entity, position: Vec2);
+    /// Moves the pointer out of `window`.
+    fn pointer_leave(&mut self, window: Entity);
+    fn pointer_button(&mut self, window: Entity, button: MouseButton, state: ButtonState);
+    /// Scrolls by `delta` pixels.
+    fn scroll(&mut self, window: Entity, delta: Vec2);
+    /// Presses or releases a key. Text is derived from `logical_key` if it is a
+    /// [`Key::Character`].
+    fn key(&mut self, window: Entity, key_code: KeyCode, logical_key: Key, state: ButtonState);
+    fn touch(&mut self, window: Entity, id: u64, phase: TouchPhase, position: Vec2);
+}
+
+impl SyntheticInput for World {
+    fn warp_pointer(&mut self, window: Entity, position: Vec2) {
+        let Some(bevy_window) = self.get::<Window>(window) else {
+            warn!("Tried to warp the pointer to a window that doesn't exist");
+            return;
+        };
+        let old_position = bevy_window.cursor_position();
+        let scale_factor = bevy_window.scale_factor();
+
+        if old_position.is_none() {
+            send_window_event(self, CursorEntered { window }.into());
+        }
+        set_cursor_position(self, window, Some(position * scale_factor));
+        let cursor_moved = CursorMoved {
+            window,
+            position,
+            delta: old_position.map(|old_position| position - old_position),
+        };
+        send_window_event(self, cursor_moved.into());
+    }
+
+    fn pointer_leave(&mut self, window: Entity) {
+        set_cursor_position(self, window, None);
+        send_window_event(self, CursorLeft { window }.into());
+    }
+
+    fn pointer_button(&mut self, window: Entity, button: MouseButton, state: ButtonState) {
+        let mouse_button_input = MouseButtonInput {
+            button,
+            state,
+            window,
+        };
+        send_window_event(self, mouse_button_input.into());
+    }
+
+    fn scroll(&mut self, window: Entity, delta: Vec2) {
+        let mouse_wheel = MouseWheel {
+            unit: MouseScrollUnit::Pixel,
+            x: delta.x,
+            y: delta.y,
+            window,
+        };
+        send_window_event(self, mouse_wheel.into());
+    }
+
+    fn key(&mut self, window: Entity, key_code: KeyCode, logical_key: Key, state: ButtonState) {
+        let text = match &logical_key {
+            Key::Character(text) => Some(text.clone()),
+            _ => None,
+        };
+        let keyboard_input = KeyboardInput {
+            key_code,
+            logical_key,
+            state,
+            text,
+            repeat: false,
+            window,
+        };
+        send_window_event(self, WindowEvent::KeyboardInput(keyboard_input));
+    }
+
+    fn touch(&mut self, window: Entity, id: u64, phase: TouchPhase, position: Vec2) {
+        let touch_input = TouchInput {
+            phase,
+            position,
+            window,
+            force: None,
+            id,
+        };
+        send_window_event(self, WindowEvent::TouchInput(touch_input));
+    }
+}
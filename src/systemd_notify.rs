@@ -0,0 +1,104 @@
+use std::{
+    os::{
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram},
+    },
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+
+/// Sends readiness, watchdog and stopping notifications to systemd.
+///
+/// Does nothing unless the app was started by a unit with `Type=notify`, i.e. `NOTIFY_SOCKET` is
+/// set. `READY=1` is sent after the first frame, `WATCHDOG=1` every half of `WatchdogSec=` and
+/// `STOPPING=1` when an [`AppExit`] event is sent, after which the runner exits. The runner wakes
+/// up for watchdog pings even while the app is idle.
+pub struct SystemdNotifyPlugin;
+impl Plugin for SystemdNotifyPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(systemd_notify) = SystemdNotify::from_env() else {
+            return;
+        };
+        info!("Systemd notifications are enabled");
+        app.insert_resource(systemd_notify);
+        app.add_systems(Last, notify_systemd);
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct SystemdNotify {
+    socket: UnixDatagram,
+    address: SocketAddr,
+    /// Interval between watchdog pings, `None` if the unit has no watchdog.
+    watchdog_interval: Option<Duration>,
+    last_watchdog_ping: Option<Instant>,
+    ready: bool,
+}
+impl SystemdNotify {
+    fn from_env() -> Option<Self> {
+        let path = std::env::var("NOTIFY_SOCKET").ok()?;
+        let address = match path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name),
+            None => SocketAddr::from_pathname(&path),
+        };
+        let address = address
+            .inspect_err(|error| error!("Invalid NOTIFY_SOCKET {:?}! {:?}", path, error))
+            .ok()?;
+        let socket = UnixDatagram::unbound()
+            .inspect_err(|error| error!("Couldn't create notify socket! {:?}", error))
+            .ok()?;
+
+        // The watchdog is meant for another process if WATCHDOG_PID doesn't match.
+        let watchdog_pid = std::env::var("WATCHDOG_PID")
+            .ok()
+            .and_then(|pid| pid.parse::<u32>().ok());
+        let watchdog_interval = std::env::var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse().ok())
+            .filter(|_| watchdog_pid.is_none_or(|pid| pid == std::process::id()))
+            .map(|usec| Duration::from_micros(usec) / 2);
+
+        Some(Self {
+            socket,
+            address,
+            watchdog_interval,
+            last_watchdog_ping: None,
+            ready: false,
+        })
+    }
+
+    /// Returns the time until the next watchdog ping is due, `None` if the unit has no watchdog.
+    pub(crate) fn time_until_watchdog_ping(&self) -> Option<Duration> {
+        let watchdog_interval = self.watchdog_interval?;
+        let since_last_ping = self
+            .last_watchdog_ping
+            .map_or(watchdog_interval, |last_ping| last_ping.elapsed());
+        Some(watchdog_interval.saturating_sub(since_last_ping))
+    }
+
+    fn notify(&self, state: &str) {
+        if let Err(error) = self.socket.send_to_addr(state.as_bytes(), &self.address) {
+            error!("Couldn't notify systemd with {:?}! {:?}", state, error);
+        }
+    }
+}
+
+fn notify_systemd(mut systemd_notify: ResMut<SystemdNotify>, mut app_exit: EventReader<AppExit>) {
+    if !systemd_notify.ready {
+        systemd_notify.notify("READY=1");
+        systemd_notify.ready = true;
+    }
+    if let Some(watchdog_interval) = systemd_notify.watchdog_interval {
+        let ping_due = systemd_notify
+            .last_watchdog_ping
+            .is_none_or(|last_ping| last_ping.elapsed() >= watchdog_interval);
+        if ping_due {
+            systemd_notify.notify("WATCHDOG=1");
+            systemd_notify.last_watchdog_ping = Some(Instant::now());
+        }
+    }
+    if app_exit.read().last().is_some() {
+        systemd_notify.notify("STOPPING=1");
+    }
+}
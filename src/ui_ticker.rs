@@ -0,0 +1,100 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+
+/// Schedules low frequency updates like a clock or a battery indicator.
+///
+/// Ticks are aligned to multiples of their period since the Unix epoch, so a clock ticking every
+/// minute updates right when the minute changes. The runner wakes up for the next tick only while
+/// a window of the app is visible, ticks missed while every window was hidden fire once when a
+/// window is shown again.
+///
+/// Systems are scheduled with the [`on_ui_tick`] run condition:
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use bevy::prelude::*;
+/// use bevy_wayland::ui_ticker::on_ui_tick;
+///
+/// # fn update_clock() {}
+/// # fn build(app: &mut App) {
+/// app.add_systems(
+///     Update,
+///     update_clock.run_if(on_ui_tick(Duration::from_secs(60))),
+/// );
+/// # }
+/// ```
+#[derive(Resource, Default)]
+pub struct UiTicker {
+    /// Periods of every `on_ui_tick` condition that ran, registered by the conditions themselves
+    /// as they can't access the world mutably.
+    periods: Mutex<Vec<Duration>>,
+    visible: bool,
+}
+impl UiTicker {
+    fn register(&self, period: Duration) {
+        let mut periods = self.periods.lock().unwrap();
+        if !periods.contains(&period) {
+            periods.push(period);
+        }
+    }
+
+    /// Returns the time until the next tick of any period, or `None` if no window is visible.
+    pub(crate) fn time_until_next_tick(&self) -> Option<Duration> {
+        if !self.visible {
+            return None;
+        }
+        let now = since_epoch();
+        self.periods
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|period| {
+                let period = period.as_millis().max(1);
+                let until_next = period - now.as_millis() % period;
+                Duration::from_millis(until_next as u64)
+            })
+            .min()
+    }
+}
+
+fn since_epoch() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Run condition which is true once every `period` while a window is visible, and on its first
+/// run.
+pub fn on_ui_tick(period: Duration) -> impl FnMut(Res<UiTicker>, Local<Option<u128>>) -> bool {
+    move |ui_ticker: Res<UiTicker>, mut last_tick: Local<Option<u128>>| {
+        ui_ticker.register(period);
+        if !ui_ticker.visible {
+            return false;
+        }
+        let tick = since_epoch().as_millis() / period.as_millis().max(1);
+        if *last_tick == Some(tick) {
+            return false;
+        }
+        *last_tick = Some(tick);
+        true
+    }
+}
+
+pub struct UiTickerPlugin;
+impl Plugin for UiTickerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UiTicker>()
+            .add_systems(PreUpdate, update_ui_ticker_visibility);
+    }
+}
+
+fn update_ui_ticker_visibility(mut ui_ticker: ResMut<UiTicker>, windows: Query<&Window>) {
+    let visible = windows.iter().any(|window| window.visible);
+    if ui_ticker.visible != visible {
+        ui_ticker.visible = visible;
+    }
+}
@@ -0,0 +1,295 @@
+use bevy::{prelude::*, window::RawHandleWrapper};
+use smithay_client_toolkit::reexports::protocols::wp::viewporter::client::wp_viewporter::WpViewporter;
+
+use crate::{
+    error::is_connected,
+    layer_shell::LayerShellSettings,
+    surface_handler::{SurfaceConfigured, WaylandSurfaces},
+    surface_viewport::SurfaceViewport,
+    WaylandState,
+};
+
+/// Releases the render target of layer shell windows hidden through [`Window::visible`] and
+/// unmaps their surfaces, so that hidden windows don't hold on to GPU memory.
+///
+/// If a [`RenderBudget`] is inserted, windows marked with [`BackgroundWindow`] are also rendered
+/// at a lower resolution while the visible windows exceed it.
+pub struct WindowVisibilityPlugin;
+impl Plugin for WindowVisibilityPlugin {
+    fn build(&self, app: &mut App) {
+        if !is_connected(app) {
+            return;
+        }
+        app.add_systems(Last, update_window_visibility).add_systems(
+            Update,
+            apply_render_budget.run_if(resource_exists::<RenderBudget>),
+        );
+    }
+}
+
+/// Limits the pixels rendered for all visible windows together.
+///
+/// While the render targets of the visible windows would exceed `max_pixels`, the windows marked
+/// with [`BackgroundWindow`] are rendered at a lower resolution and scaled up by the compositor
+/// through a [`SurfaceViewport`], which requires `wp_viewporter`. The resolution is scaled by the
+/// same factor for all of them, but never below `min_scale`.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct RenderBudget {
+    /// Maximum number of physical pixels of all visible render targets.
+    pub max_pixels: u32,
+    /// Lowest factor the resolution of background windows is scaled by.
+    pub min_scale: f32,
+}
+impl Default for RenderBudget {
+    fn default() -> Self {
+        Self {
+            max_pixels: 1920 * 1080 * 2,
+            min_scale: 0.5,
+        }
+    }
+}
+
+/// Marks a window which may be rendered at a lower resolution to stay within the
+/// [`RenderBudget`], e.g. a launcher grid behind other surfaces.
+///
+/// Windows with a [`SurfaceViewport`] set by the app are never downscaled.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct BackgroundWindow;
+
+/// A background window rendered below its resolution.
+#[derive(Component)]
+struct Downscaled {
+    /// Scale factor override of the window before it was downscaled.
+    scale_factor_override: Option<f32>,
+}
+impl Downscaled {
+    fn native_scale_factor(&self, window: &Window) -> f32 {
+        self.scale_factor_override
+            .unwrap_or(window.resolution.base_scale_factor())
+    }
+}
+
+/// Returns the factor the resolution of background windows has to be scaled by to render at most
+/// `max_pixels`, given the pixels of foreground and background windows at their native resolution.
+fn budget_scale(
+    render_budget: &RenderBudget,
+    foreground_pixels: f32,
+    background_pixels: f32,
+) -> f32 {
+    let max_pixels = render_budget.max_pixels as f32;
+    if background_pixels <= 0.0 || foreground_pixels + background_pixels <= max_pixels {
+        return 1.0;
+    }
+    // Pixels scale with the square of the resolution.
+    ((max_pixels - foreground_pixels).max(0.0) / background_pixels)
+        .sqrt()
+        .clamp(render_budget.min_scale.min(1.0), 1.0)
+}
+
+/// Changes the scale factor of `window` while keeping its logical size, so only the resolution of
+/// its render target changes.
+fn set_scale_factor_override(window: &mut Window, scale_factor_override: Option<f32>) {
+    let (width, height) = (window.width(), window.height());
+    window
+        .resolution
+        .set_scale_factor_override(scale_factor_override);
+    window.resolution.set(width, height);
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_render_budget(
+    mut commands: Commands,
+    render_budget: Res<RenderBudget>,
+    viewporter: Option<NonSend<WpViewporter>>,
+    mut windows: Query<
+        (
+            Entity,
+            &mut Window,
+            Has<BackgroundWindow>,
+            Option<&Downscaled>,
+            Option<&mut SurfaceViewport>,
+        ),
+        With<SurfaceConfigured>,
+    >,
+    mut viewporter_missing_reported: Local<bool>,
+) {
+    if viewporter.is_none() {
+        if !*viewporter_missing_reported {
+            warn!("The compositor doesn't support wp_viewporter, background windows aren't downscaled");
+            *viewporter_missing_reported = true;
+        }
+        return;
+    }
+
+    let mut foreground_pixels = 0.0;
+    let mut background_pixels = 0.0;
+    for (_, window, background, downscaled, surface_viewport) in &windows {
+        if !window.visible {
+            continue;
+        }
+        let scale_factor = downscaled.map_or(window.scale_factor(), |downscaled| {
+            downscaled.native_scale_factor(window)
+        });
+        let pixels = window.width() * window.height() * scale_factor * scale_factor;
+        if background && (surface_viewport.is_none() || downscaled.is_some()) {
+            background_pixels += pixels;
+        } else {
+            foreground_pixels += pixels;
+        }
+    }
+    let scale = budget_scale(&render_budget, foreground_pixels, background_pixels);
+
+    for (entity, mut window, background, downscaled, surface_viewport) in &mut windows {
+        if !background || !window.visible || (surface_viewport.is_some() && downscaled.is_none()) {
+            continue;
+        }
+        if scale < 1.0 {
+            let native_scale_factor = downscaled.map_or(window.scale_factor(), |downscaled| {
+                downscaled.native_scale_factor(&window)
+            });
+            let scale_factor = native_scale_factor * scale;
+            if (window.scale_factor() - scale_factor).abs() > 0.01 {
+                if downscaled.is_none() {
+                    commands.entity(entity).insert(Downscaled {
+                        scale_factor_override: window.resolution.scale_factor_override(),
+                    });
+                }
+                set_scale_factor_override(&mut window, Some(scale_factor));
+            }
+            // The viewport keeps the surface at the logical size of the window.
+            let downscaled_viewport = SurfaceViewport {
+                source: None,
+                destination: Some(UVec2::new(window.width() as u32, window.height() as u32)),
+            };
+            match surface_viewport {
+                Some(mut surface_viewport) => {
+                    surface_viewport.set_if_neq(downscaled_viewport);
+                }
+                None => {
+                    commands.entity(entity).insert(downscaled_viewport);
+                }
+            }
+        } else if let Some(downscaled) = downscaled {
+            set_scale_factor_override(&mut window, downscaled.scale_factor_override);
+            commands
+                .entity(entity)
+                .remove::<(Downscaled, SurfaceViewport)>();
+        }
+    }
+}
+
+/// Render target of a hidden window, kept to give it back once the window is shown again.
+#[derive(Component)]
+pub(crate) struct HiddenWindow {
+    raw_handle_wrapper: RawHandleWrapper,
+    /// Set once the surface was committed again and is waiting for the compositor to configure it.
+    remapping: bool,
+}
+
+#[allow(clippy::type_complexity)]
+fn update_window_visibility(
+    mut commands: Commands,
+    wayland_surfaces: NonSend<WaylandSurfaces>,
+    mut windows: Query<
+        (
+            Entity,
+            &Window,
+            Option<&RawHandleWrapper>,
+            Option<&mut HiddenWindow>,
+        ),
+        (
+            With<LayerShellSettings>,
+            With<SurfaceConfigured>,
+            Changed<Window>,
+        ),
+    >,
+    mut windows_to_unmap: Local<Vec<Entity>>,
+) {
+    // Surfaces are unmapped one frame after their render target was removed, once the render world
+    // stopped presenting to them.
+    for entity in windows_to_unmap.drain(..) {
+        if let Some(window_wrapper) = wayland_surfaces.get_window_wrapper(entity) {
+            let wl_surface = window_wrapper.wl_surface();
+            wl_surface.attach(None, 0, 0);
+            wl_surface.commit();
+        }
+    }
+
+    for (entity, window, raw_handle_wrapper, hidden_window) in &mut windows {
+        match (window.visible, raw_handle_wrapper, hidden_window) {
+            (false, Some(raw_handle_wrapper), None) => {
+                commands
+                    .entity(entity)
+                    .remove::<RawHandleWrapper>()
+                    .insert(HiddenWindow {
+                        raw_handle_wrapper: raw_handle_wrapper.clone(),
+                        remapping: false,
+                    });
+                windows_to_unmap.push(entity);
+            }
+            (true, _, Some(mut hidden_window)) if !hidden_window.remapping => {
+                // Committing the unmapped surface makes the compositor configure it again, the
+                // render target is only given back after that.
+                hidden_window.remapping = true;
+                if let Some(window_wrapper) = wayland_surfaces.get_window_wrapper(entity) {
+                    window_wrapper.wl_surface().commit();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl WaylandState {
+    /// Gives a hidden window its render target back once its surface was configured again.
+    pub(crate) fn remap_hidden_window(&mut self, window: Entity) {
+        let Ok(mut entity) = self.world_mut().get_entity_mut(window) else {
+            return;
+        };
+        let visible = entity
+            .get::<Window>()
+            .is_some_and(|bevy_window| bevy_window.visible);
+        let Some(mut hidden_window) = entity.get_mut::<HiddenWindow>() else {
+            return;
+        };
+        if !hidden_window.remapping {
+            return;
+        }
+        if !visible {
+            // Hidden again while waiting for the configure, the surface is still unmapped.
+            hidden_window.remapping = false;
+            return;
+        }
+        let raw_handle_wrapper = hidden_window.raw_handle_wrapper.clone();
+        entity.remove::<HiddenWindow>().insert(raw_handle_wrapper);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUDGET: RenderBudget = RenderBudget {
+        max_pixels: 1000,
+        min_scale: 0.25,
+    };
+
+    #[test]
+    fn budget_scale_within_budget() {
+        assert_eq!(budget_scale(&BUDGET, 500.0, 500.0), 1.0);
+        assert_eq!(budget_scale(&BUDGET, 2000.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn budget_scale_over_budget() {
+        // 400 pixels are left for 1600 background pixels, a quarter of the pixels is half the
+        // resolution.
+        assert_eq!(budget_scale(&BUDGET, 600.0, 1600.0), 0.5);
+    }
+
+    #[test]
+    fn budget_scale_clamped_to_min_scale() {
+        assert_eq!(budget_scale(&BUDGET, 1000.0, 1000.0), 0.25);
+        assert_eq!(budget_scale(&BUDGET, 2000.0, 1000.0), 0.25);
+    }
+}
@@ -0,0 +1,68 @@
+//! Drives a UI through `SyntheticInput` without a compositor.
+use bevy::{
+    input::ButtonState,
+    log::LogPlugin,
+    prelude::*,
+    render::{settings::WgpuSettings, RenderPlugin},
+    window::WindowResolution,
+    winit::WinitPlugin,
+};
+use bevy_wayland::synthetic_input::SyntheticInput;
+
+/// An app with a 200x100 primary window, which neither opens a window nor renders.
+fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: WindowResolution::new(200.0, 100.0),
+                    ..default()
+                }),
+                ..default()
+            })
+            .set(RenderPlugin {
+                render_creation: WgpuSettings {
+                    backends: None,
+                    ..default()
+                }
+                .into(),
+                ..default()
+            })
+            .disable::<WinitPlugin>()
+            .disable::<LogPlugin>(),
+    );
+    app
+}
+
+#[test]
+fn pointer_button_presses_button() {
+    let mut app = headless_app();
+    app.world_mut().spawn(Camera2d);
+    let button = app
+        .world_mut()
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(50.0),
+                height: Val::Px(50.0),
+                ..default()
+            },
+        ))
+        .id();
+    let window = app
+        .world_mut()
+        .query_filtered::<Entity, With<Window>>()
+        .single(app.world())
+        .unwrap();
+    app.update();
+
+    app.world_mut().warp_pointer(window, Vec2::new(20.0, 20.0));
+    app.update();
+    assert_eq!(app.world().get::<Interaction>(button), Some(&Interaction::Hovered));
+
+    app.world_mut()
+        .pointer_button(window, MouseButton::Left, ButtonState::Pressed);
+    app.update();
+    assert_eq!(app.world().get::<Interaction>(button), Some(&Interaction::Pressed));
+}